@@ -0,0 +1,621 @@
+// Base plate floors
+// a floor under the base plate sockets , solid or cut back to a
+// cross shaped rib under each cell , with countersunk screw holes
+// for fixing the plate down , and a french cleat or keyholes on
+// the back for hanging it on a wall
+
+use clap::ValueEnum;
+use glam::{dvec2, dvec3, DVec2, DVec3};
+use opencascade::{
+    primitives::{Shape, Solid},
+    workplane::Workplane,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::gridfin::{Connector, FILLET, SIZE};
+use crate::mask::Mask;
+use crate::outline::Outline;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bottom {
+    // just the socket frame
+    Open,
+    // a full floor under the frame
+    Solid,
+    // a floor with each cell cut back to a cross shaped rib
+    Skeleton,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Placement {
+    // one in the middle of each cell
+    Center,
+    // four in each cell , where the magnets of the bin sit
+    Corners,
+}
+
+#[derive(Debug, Clone)]
+pub struct Screws {
+    pub placement: Placement,
+    // shank clearance
+    pub diameter: f64,
+    // countersink diameter at the top of the floor
+    pub head: f64,
+}
+
+impl Screws {
+    pub fn new(placement: Placement) -> Self {
+        // an M3 countersunk screw
+        Self {
+            placement,
+            diameter: 3.4,
+            head: 6.4,
+        }
+    }
+
+    // a 90 degree countersink is half the difference deep
+    fn sink(&self) -> f64 {
+        (self.head - self.diameter) / 2.0
+    }
+
+    pub fn check(&self, floor: f64) -> Result<(), String> {
+        if self.diameter <= 0.0 || self.head <= self.diameter {
+            return Err("the screw head must be wider than the shank".to_owned());
+        }
+        if self.head > BaseFloor::MAX_HEAD {
+            return Err(format!(
+                "a {}mm screw head is wider than the {}mm a cell takes",
+                self.head,
+                BaseFloor::MAX_HEAD
+            ));
+        }
+        if self.sink() + BaseFloor::MIN_BELOW > floor {
+            return Err(format!(
+                "a {}mm floor is too thin for a {}mm countersink",
+                floor,
+                self.sink()
+            ));
+        }
+        Ok(())
+    }
+
+    // hole centers from the center of a cell
+    fn offsets(&self) -> Vec<DVec2> {
+        match self.placement {
+            Placement::Center => vec![dvec2(0.0, 0.0)],
+            Placement::Corners => Connector::magnets().to_vec(),
+        }
+    }
+
+    // every hole center on the plate
+    pub fn centers(&self, mask: &Mask) -> Vec<DVec2> {
+        mask.cells()
+            .flat_map(|(x, y)| {
+                let cell = mask.cell_center(x, y);
+                self.offsets().into_iter().map(move |o| cell + o)
+            })
+            .collect()
+    }
+
+    // a shank through the floor with the countersink at the top
+    fn hole(&self, center: DVec2, floor: f64) -> Shape {
+        let shank = Workplane::xy()
+            .circle(center.x, center.y, self.diameter / 2.0)
+            .translate(dvec3(0.0, 0.0, -1.0));
+        let mut hole: Shape = shank.to_face().extrude(dvec3(0.0, 0.0, floor + 2.0)).into();
+        let bottom = Workplane::xy()
+            .circle(center.x, center.y, self.diameter / 2.0)
+            .translate(dvec3(0.0, 0.0, floor - self.sink()));
+        // carried on past the top so the faces don't touch
+        let top = Workplane::xy()
+            .circle(center.x, center.y, self.head / 2.0 + 0.5)
+            .translate(dvec3(0.0, 0.0, floor + 0.5));
+        let sink = Solid::loft([&bottom, &top]).into();
+        hole = hole.union(&sink).into();
+        hole
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hanger {
+    // a bevelled strip that hooks over a matching strip on the wall
+    Cleat,
+    // slots that drop over screw heads
+    Keyhole,
+}
+
+// a strip across the back near the top , the bottom edge bevelled so
+// it drops into the groove behind a wall cleat , the plate's top edge
+// is the back row of cells
+#[derive(Debug, Clone)]
+pub struct Cleat {
+    // bevel from the wall in degrees
+    pub angle: f64,
+    // how far it stands off the back
+    pub thickness: f64,
+    // top to the tip of the bevel
+    pub height: f64,
+}
+
+impl Default for Cleat {
+    fn default() -> Self {
+        Self {
+            angle: 45.0,
+            thickness: 6.0,
+            height: 20.0,
+        }
+    }
+}
+
+impl Cleat {
+    // how far up the bevel runs
+    fn bevel(&self) -> f64 {
+        self.thickness / self.angle.to_radians().tan()
+    }
+
+    pub fn check(&self, mask: &Mask) -> Result<(), String> {
+        if !(30.0..=60.0).contains(&self.angle) {
+            return Err(format!(
+                "cleat angle {} is not between 30 and 60",
+                self.angle
+            ));
+        }
+        if self.thickness <= 0.0 {
+            return Err("cleat thickness must be more than zero".to_owned());
+        }
+        if self.height <= self.bevel() {
+            return Err(format!(
+                "a {}mm cleat is too short for its {:.1}mm bevel",
+                self.height,
+                self.bevel()
+            ));
+        }
+        if self.height > SIZE * mask.height() as f64 {
+            return Err(format!(
+                "a {}mm cleat is taller than the plate",
+                self.height
+            ));
+        }
+        Ok(())
+    }
+
+    // the strip below the back of the plate , cut to the footprint
+    fn shape(&self, mask: &Mask) -> Shape {
+        let width = SIZE * mask.width() as f64;
+        let top = SIZE * mask.height() as f64 / 2.0;
+        let bottom = top - self.height;
+        // (up the plate , out from the back) , the tip is on the wall side
+        let profile = Outline::new(vec![
+            dvec2(top, 0.0),
+            dvec2(top, self.thickness),
+            dvec2(bottom, self.thickness),
+            dvec2(bottom + self.bevel(), 0.0),
+        ])
+        .unwrap();
+        let origin = dvec3(-width / 2.0, 0.0, 0.0);
+        let strip = profile.prism(origin, DVec3::Y, -DVec3::Z, dvec3(width, 0.0, 0.0));
+        let footprint: Shape = mask
+            .outline(0.0, FILLET)
+            .translate(dvec3(0.0, 0.0, -self.thickness - 1.0))
+            .to_face()
+            .extrude(dvec3(0.0, 0.0, self.thickness + 1.0))
+            .into();
+        strip.intersect(&footprint).into()
+    }
+}
+
+// keyhole slots in the back of the floor , a round entry for the
+// screw head and a narrow slot up from it with room behind for the head
+#[derive(Debug, Clone)]
+pub struct Keyholes {
+    // between the two keyholes , none for one in each outer column
+    pub spacing: Option<f64>,
+    pub head: f64,
+    pub shank: f64,
+    // how far the head slides up
+    pub length: f64,
+    // material the head hooks behind
+    pub lip: f64,
+    // room for the head behind the lip
+    pub channel: f64,
+}
+
+impl Default for Keyholes {
+    fn default() -> Self {
+        Self {
+            spacing: None,
+            head: 8.0,
+            shank: 4.0,
+            length: 10.0,
+            lip: 1.5,
+            channel: 3.0,
+        }
+    }
+}
+
+impl Keyholes {
+    // entry centers , in the back row of cells
+    fn centers(&self, mask: &Mask) -> Vec<DVec2> {
+        let y = SIZE * (mask.height() - 1) as f64 / 2.0 - self.length / 2.0;
+        let spacing = self.spacing.unwrap_or(SIZE * (mask.width() - 1) as f64);
+        if spacing <= 0.0 {
+            vec![dvec2(0.0, y)]
+        } else {
+            vec![dvec2(-spacing / 2.0, y), dvec2(spacing / 2.0, y)]
+        }
+    }
+
+    // the thinnest floor the lip and channel fit in
+    pub fn floor(&self) -> f64 {
+        self.lip + self.channel + BaseFloor::MIN_BELOW
+    }
+
+    pub fn check(&self, mask: &Mask, floor: f64) -> Result<(), String> {
+        if self.shank <= 0.0 || self.head <= self.shank {
+            return Err("the keyhole head must be wider than the shank".to_owned());
+        }
+        if self.floor() > floor {
+            return Err(format!(
+                "keyholes need a floor at least {}mm thick",
+                self.floor()
+            ));
+        }
+        for c in self.centers(mask) {
+            let reach = self.head / 2.0 + BaseFloor::BORDER;
+            let ends = [c - dvec2(0.0, reach), c + dvec2(0.0, self.length + reach)];
+            if !ends.iter().all(|p| mask.contains(*p)) {
+                return Err(format!(
+                    "a keyhole at {:.1},{:.1} is off the plate",
+                    c.x, c.y
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // a round or slot outline , `width` across , from z up by `depth`
+    fn stadium(&self, center: DVec2, width: f64, length: f64, z: f64, depth: f64) -> Shape {
+        let r = width / 2.0;
+        let end = center + dvec2(0.0, length);
+        let mut shape: Shape = Workplane::xy()
+            .circle(center.x, center.y, r)
+            .translate(dvec3(0.0, 0.0, z))
+            .to_face()
+            .extrude(dvec3(0.0, 0.0, depth))
+            .into();
+        if length > 0.0 {
+            let middle = dvec3(center.x, center.y + length / 2.0, z);
+            let rect = Workplane::xy().rect(width, length).translate(middle);
+            let slot: Shape = rect.to_face().extrude(dvec3(0.0, 0.0, depth)).into();
+            let top: Shape = Workplane::xy()
+                .circle(end.x, end.y, r)
+                .translate(dvec3(0.0, 0.0, z))
+                .to_face()
+                .extrude(dvec3(0.0, 0.0, depth))
+                .into();
+            shape = shape.union(&slot).into();
+            shape = shape.union(&top).into();
+        }
+        shape
+    }
+
+    // the cutter up into the back of the floor
+    fn cutter(&self, mask: &Mask) -> Shape {
+        let inside = self.lip + self.channel;
+        let mut cutter: Option<Shape> = None;
+        for c in self.centers(mask) {
+            // in from the back at the entry , through the lip along the slot
+            let entry = self.stadium(c, self.head, 0.0, -1.0, inside + 1.0);
+            let slot = self.stadium(c, self.shank, self.length, -1.0, self.lip + 1.0);
+            let channel = self.stadium(c, self.head, self.length, self.lip, self.channel);
+            let mut hole: Shape = entry.union(&slot).into();
+            hole = hole.union(&channel).into();
+            cutter = Some(match cutter {
+                Some(s) => s.union(&hole).into(),
+                None => hole,
+            });
+        }
+        cutter.unwrap()
+    }
+}
+
+// how the plate hangs on a wall
+#[derive(Debug, Clone)]
+pub enum Mount {
+    Cleat(Cleat),
+    Keyholes(Keyholes),
+}
+
+// the floor settings for a base plate
+#[derive(Debug, Clone)]
+pub struct BaseFloor {
+    pub bottom: Bottom,
+    pub thickness: f64,
+    pub screws: Option<Screws>,
+    pub mount: Option<Mount>,
+}
+
+impl BaseFloor {
+    // keep this much under a countersink
+    const MIN_BELOW: f64 = 1.0;
+    // width of the rib arms under a skeleton cell
+    const RIB: f64 = 6.0;
+    // material left between the cut outs and the cell edge
+    const BORDER: f64 = 3.0;
+    // material around a screw hole in a skeleton cell
+    const BOSS: f64 = 2.0;
+    // the socket floor is this wide , keep the heads on it
+    const MAX_HEAD: f64 = 12.0;
+
+    pub fn new(bottom: Bottom) -> Self {
+        Self {
+            bottom,
+            thickness: 4.0,
+            screws: None,
+            mount: None,
+        }
+    }
+
+    // no floor under the frame
+    pub fn open() -> Self {
+        Self::new(Bottom::Open)
+    }
+
+    // keyholes are cut into a solid floor , so an open plate gets one
+    // and a thin floor is raised until the channel fits
+    pub fn set_mount(&mut self, mount: Mount) {
+        if let Mount::Keyholes(keyholes) = &mount {
+            if self.bottom == Bottom::Open {
+                self.bottom = Bottom::Solid;
+            }
+            self.thickness = self.thickness.max(keyholes.floor());
+        }
+        self.mount = Some(mount);
+    }
+
+    // how far the sockets are lifted
+    pub fn height(&self) -> f64 {
+        match self.bottom {
+            Bottom::Open => 0.0,
+            _ => self.thickness,
+        }
+    }
+
+    pub fn check(&self, mask: &Mask) -> Result<(), String> {
+        match &self.mount {
+            Some(Mount::Cleat(cleat)) => cleat.check(mask)?,
+            Some(Mount::Keyholes(keyholes)) => {
+                if self.bottom != Bottom::Solid {
+                    return Err("keyholes need a solid floor".to_owned());
+                }
+                keyholes.check(mask, self.thickness)?;
+            }
+            None => {}
+        }
+        if self.bottom == Bottom::Open {
+            if self.screws.is_some() {
+                return Err("screw holes need a solid or skeleton floor".to_owned());
+            }
+            return Ok(());
+        }
+        if self.thickness <= 0.0 {
+            return Err("floor thickness must be more than zero".to_owned());
+        }
+        if let Some(screws) = &self.screws {
+            screws.check(self.thickness)?;
+        }
+        Ok(())
+    }
+
+    // the cleat or keyholes on the back of the finished plate
+    pub fn hang(&self, mask: &Mask, plate: Shape) -> Shape {
+        match &self.mount {
+            Some(Mount::Cleat(cleat)) => plate.union(&cleat.shape(mask)).into(),
+            Some(Mount::Keyholes(keyholes)) => plate.subtract(&keyholes.cutter(mask)).into(),
+            None => plate,
+        }
+    }
+
+    // the quarters of a cell outside the rib , less the bosses
+    fn skeleton(&self, mask: &Mask) -> Option<Shape> {
+        let half = Connector::LOWER_SIZE / 2.0 - BaseFloor::BORDER;
+        let side = half - BaseFloor::RIB / 2.0;
+        if side <= 0.0 {
+            return None;
+        }
+        let mut cutter: Option<Shape> = None;
+        for (x, y) in mask.cells() {
+            let cell = mask.cell_center(x, y);
+            for (sx, sy) in [(1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)] {
+                let c = cell + dvec2(sx, sy) * (BaseFloor::RIB / 2.0 + side / 2.0);
+                let rect = Workplane::xy()
+                    .rect(side, side)
+                    .translate(dvec3(c.x, c.y, -1.0));
+                let quarter: Shape = rect
+                    .to_face()
+                    .extrude(dvec3(0.0, 0.0, self.thickness + 2.0))
+                    .into();
+                cutter = Some(match cutter {
+                    Some(c) => c.union(&quarter).into(),
+                    None => quarter,
+                });
+            }
+        }
+        let mut cutter = cutter?;
+        // leave material round the screws
+        if let Some(screws) = &self.screws {
+            for center in screws.centers(mask) {
+                let boss = Workplane::xy()
+                    .circle(center.x, center.y, screws.head / 2.0 + BaseFloor::BOSS)
+                    .translate(dvec3(0.0, 0.0, -2.0));
+                let boss: Shape = boss
+                    .to_face()
+                    .extrude(dvec3(0.0, 0.0, self.thickness + 4.0))
+                    .into();
+                cutter = cutter.subtract(&boss).into();
+            }
+        }
+        Some(cutter)
+    }
+
+    // the floor itself , from z = 0 up to the bottom of the sockets
+    pub fn shape(&self, mask: &Mask, fillet: f64) -> Option<Shape> {
+        if self.bottom == Bottom::Open {
+            return None;
+        }
+        let mut floor: Shape = mask
+            .outline(0.0, fillet)
+            .to_face()
+            .extrude(dvec3(0.0, 0.0, self.thickness))
+            .into();
+        if self.bottom == Bottom::Skeleton {
+            if let Some(cutter) = self.skeleton(mask) {
+                floor = floor.subtract(&cutter).into();
+            }
+        }
+        if let Some(screws) = &self.screws {
+            for center in screws.centers(mask) {
+                floor = floor.subtract(&screws.hole(center, self.thickness)).into();
+            }
+        }
+        Some(floor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countersinks_are_half_the_difference_deep() {
+        let screws = Screws::new(Placement::Center);
+        assert!((screws.sink() - 1.5).abs() < 1e-9);
+        assert!(screws.check(4.0).is_ok());
+        // 1.5mm of sink and 1mm below it
+        assert!(screws.check(2.5).is_ok());
+        let err = screws.check(2.4).unwrap_err();
+        assert!(err.contains("too thin"), "{}", err);
+    }
+
+    #[test]
+    fn bad_screws() {
+        let mut screws = Screws::new(Placement::Center);
+        screws.head = screws.diameter;
+        assert!(screws.check(4.0).is_err());
+        screws.diameter = 0.0;
+        screws.head = 6.0;
+        assert!(screws.check(4.0).is_err());
+        screws.diameter = 3.4;
+        screws.head = 13.0;
+        let err = screws.check(10.0).unwrap_err();
+        assert!(err.contains("wider than"), "{}", err);
+    }
+
+    #[test]
+    fn screws_go_in_every_cell() {
+        let ell = Mask::from_ascii("#.\n##\n").unwrap();
+        let center = Screws::new(Placement::Center).centers(&ell);
+        assert_eq!(center.len(), 3);
+        for (x, y) in ell.cells() {
+            assert!(center.contains(&ell.cell_center(x, y)));
+        }
+        let corners = Screws::new(Placement::Corners).centers(&ell);
+        assert_eq!(corners.len(), 12);
+        assert!(corners.iter().all(|c| ell.contains(*c)));
+    }
+
+    #[test]
+    fn open_floors_lift_nothing() {
+        assert_eq!(BaseFloor::open().height(), 0.0);
+        let mut floor = BaseFloor::new(Bottom::Skeleton);
+        assert_eq!(floor.height(), 4.0);
+        floor.thickness = 6.0;
+        assert_eq!(floor.height(), 6.0);
+    }
+
+    #[test]
+    fn floors_hold_their_screws() {
+        let mask = Mask::rect(2, 2);
+        let mut floor = BaseFloor::open();
+        assert!(floor.check(&mask).is_ok());
+        floor.screws = Some(Screws::new(Placement::Corners));
+        assert_eq!(
+            floor.check(&mask).unwrap_err(),
+            "screw holes need a solid or skeleton floor"
+        );
+        floor.bottom = Bottom::Skeleton;
+        assert!(floor.check(&mask).is_ok());
+        floor.thickness = 2.0;
+        assert!(floor.check(&mask).is_err());
+        floor.thickness = 0.0;
+        floor.screws = None;
+        assert!(floor.check(&mask).is_err());
+    }
+
+    #[test]
+    fn default_keyholes_fit() {
+        let mask = Mask::rect(3, 2);
+        let mut floor = BaseFloor::open();
+        floor.set_mount(Mount::Keyholes(Keyholes::default()));
+        assert_eq!(floor.bottom, Bottom::Solid);
+        assert_eq!(floor.thickness, 5.5);
+        assert!(floor.check(&mask).is_ok());
+        // a thicker floor is left alone
+        let mut floor = BaseFloor::new(Bottom::Solid);
+        floor.thickness = 8.0;
+        floor.set_mount(Mount::Keyholes(Keyholes::default()));
+        assert_eq!(floor.thickness, 8.0);
+        // a skeleton is asked for , not swapped
+        let mut floor = BaseFloor::new(Bottom::Skeleton);
+        floor.set_mount(Mount::Keyholes(Keyholes::default()));
+        assert_eq!(
+            floor.check(&mask).unwrap_err(),
+            "keyholes need a solid floor"
+        );
+    }
+
+    #[test]
+    fn keyholes_sit_in_the_back_row() {
+        let keyholes = Keyholes::default();
+        let mask = Mask::rect(3, 2);
+        let y = SIZE / 2.0 - keyholes.length / 2.0;
+        assert_eq!(keyholes.centers(&mask), [dvec2(-SIZE, y), dvec2(SIZE, y)]);
+        // one in the middle of a single column
+        assert_eq!(keyholes.centers(&Mask::rect(1, 2)), [dvec2(0.0, y)]);
+        // too far apart for the plate
+        let spaced = Keyholes {
+            spacing: Some(4.0 * SIZE),
+            ..Default::default()
+        };
+        assert!(spaced.check(&mask, 6.0).is_err());
+    }
+
+    #[test]
+    fn default_cleats_fit() {
+        let mask = Mask::rect(3, 2);
+        let mut floor = BaseFloor::open();
+        floor.set_mount(Mount::Cleat(Cleat::default()));
+        assert_eq!(floor.bottom, Bottom::Open);
+        assert!(floor.check(&mask).is_ok());
+        assert!((Cleat::default().bevel() - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bad_cleats() {
+        let mask = Mask::rect(3, 1);
+        let cleat = |angle, thickness, height| Cleat {
+            angle,
+            thickness,
+            height,
+        };
+        assert!(cleat(20.0, 6.0, 20.0).check(&mask).is_err());
+        assert!(cleat(45.0, 0.0, 20.0).check(&mask).is_err());
+        // shorter than its own bevel
+        assert!(cleat(30.0, 6.0, 10.0).check(&mask).is_err());
+        // taller than one row of cells
+        assert!(cleat(45.0, 6.0, 50.0).check(&mask).is_err());
+    }
+}
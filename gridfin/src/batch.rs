@@ -0,0 +1,62 @@
+// Batch runs
+// a json manifest lists the parts , each one is made and written to
+// the output folder , with estimates for the whole lot if asked
+
+use std::fs;
+use std::path::Path;
+
+use crate::export::write;
+use crate::params::Params;
+use crate::report::{Material, Report};
+
+// a json list of part settings , the same as the server takes
+pub fn load(path: &str) -> Result<Vec<Params>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let parts: Vec<Params> = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if parts.is_empty() {
+        return Err(format!("{}: no parts listed", path));
+    }
+    Ok(parts)
+}
+
+// every entry checked , with the entry number on the errors
+pub fn check(parts: &[Params]) -> Result<(), String> {
+    for (n, params) in parts.iter().enumerate() {
+        params
+            .check()
+            .map_err(|e| format!("part {} : {}", n + 1, e))?;
+    }
+    Ok(())
+}
+
+// make every part , the reports come back when a material is given
+pub fn run(
+    parts: &[Params],
+    out: &str,
+    material: Option<&Material>,
+) -> Result<Vec<Report>, String> {
+    check(parts)?;
+    fs::create_dir_all(out).map_err(|e| format!("{}: {}", out, e))?;
+    let mut reports = Vec::new();
+    for (n, params) in parts.iter().enumerate() {
+        let stem = match &params.name {
+            Some(name) => name.clone(),
+            None => format!("part_{}", n + 1),
+        };
+        let name = Path::new(out)
+            .join(format!("{}.{}", stem, params.format.ext()))
+            .to_string_lossy()
+            .to_string();
+        println!("output : {:?}", name);
+        let bodies = params
+            .bodies()
+            .map_err(|e| format!("part {} : {}", n + 1, e))?;
+        if let Some(material) = material {
+            let report = Report::new(&stem, &bodies, material);
+            println!("{}", report.text());
+            reports.push(report);
+        }
+        write(&name, params.format, bodies)?;
+    }
+    Ok(reports)
+}
@@ -0,0 +1,127 @@
+// Shape cache
+// finished shapes are kept in memory and as brep files on disk ,
+// keyed by the generator , its settings and the crate version , so
+// the same boolean work is only ever done once
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use opencascade::primitives::Shape;
+
+// where the brep files go , none keeps the cache in memory only
+static DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+// shapes kept in memory , the least recently used go first so a
+// long running server doesn't grow without bound
+const MEMORY_LIMIT: usize = 64;
+
+#[derive(Default)]
+struct Memory {
+    // the shape and when it was last used
+    shapes: HashMap<String, (u64, Shape)>,
+    clock: u64,
+}
+
+impl Memory {
+    fn get(&mut self, key: &str) -> Option<Shape> {
+        self.clock += 1;
+        let (used, shape) = self.shapes.get_mut(key)?;
+        *used = self.clock;
+        Some(shape.clone())
+    }
+
+    fn insert(&mut self, key: String, shape: Shape) {
+        self.clock += 1;
+        if self.shapes.len() >= MEMORY_LIMIT && !self.shapes.contains_key(&key) {
+            let oldest = self
+                .shapes
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                self.shapes.remove(&oldest);
+            }
+        }
+        self.shapes.insert(key, (self.clock, shape));
+    }
+}
+
+thread_local! {
+    static MEMORY: RefCell<Memory> = RefCell::new(Memory::default());
+}
+
+// set once at start up , before any shapes are made
+pub fn set_dir(dir: Option<PathBuf>) {
+    let _ = DIR.set(dir);
+}
+
+// $XDG_CACHE_HOME/gridfin or ~/.cache/gridfin
+pub fn default_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("gridfin"))
+}
+
+// fnv-1a , stable between runs and builds unlike the std hasher
+pub fn fnv(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub fn key(generator: &str, params: &str) -> String {
+    let text = format!("{}\n{}\n{}", env!("CARGO_PKG_VERSION"), generator, params);
+    format!("{}_{:016x}", generator, fnv(text.as_bytes()))
+}
+
+// the shape for `generator` with `params` , built only if it is
+// not in memory or on disk yet
+pub fn shape<P: Debug>(generator: &str, params: &P, build: impl FnOnce() -> Shape) -> Shape {
+    let key = key(generator, &format!("{:?}", params));
+    if let Some(shape) = MEMORY.with(|m| m.borrow_mut().get(&key)) {
+        return shape;
+    }
+    let path = DIR
+        .get()
+        .and_then(|dir| dir.as_ref())
+        .map(|dir| dir.join(format!("{}.brep", key)));
+    let stored = path
+        .as_ref()
+        .filter(|p| p.exists())
+        .and_then(|p| Shape::read_brep(&p.to_string_lossy()).ok());
+    let shape = match stored {
+        Some(shape) => shape,
+        None => {
+            let shape = build();
+            if let Some(path) = &path {
+                if let Err(e) = store(&shape, path) {
+                    eprintln!("shape cache : {}", e);
+                }
+            }
+            shape
+        }
+    };
+    MEMORY.with(|m| m.borrow_mut().insert(key, shape.clone()));
+    shape
+}
+
+// write beside the entry and move it in , so a half written
+// file is never read back
+fn store(shape: &Shape, path: &PathBuf) -> Result<(), String> {
+    let dir = path.parent().unwrap();
+    fs::create_dir_all(dir).map_err(|e| format!("{:?}: {}", dir, e))?;
+    let temp = path.with_extension(format!("{}.part", std::process::id()));
+    shape
+        .write_brep(&temp.to_string_lossy())
+        .map_err(|e| format!("{:?}: {:?}", temp, e))?;
+    fs::rename(&temp, path).map_err(|e| format!("{:?}: {}", path, e))
+}
@@ -0,0 +1,494 @@
+// Carrying cases
+// a box with a base plate floor for bins of a given height , and a
+// lid hinged on the back with snap latches on the front and a
+// handle under them . the two halves are laid out open , flat on
+// the bed , so the hinge can print in place . closing the lid turns
+// it half a turn about the hinge axis , the lid parts are drawn
+// where they are when the case is closed and turned with `flip`
+
+use clap::ValueEnum;
+use glam::{dvec2, dvec3, DVec2, DVec3};
+use opencascade::primitives::Shape;
+
+use crate::base::{BaseFloor, Bottom};
+use crate::cache;
+use crate::gridfin::{total_height, BasePlate, FILLET, SIZE};
+use crate::mask::Mask;
+use crate::outline::Outline;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Hinge {
+    // a pin joined to the case knuckles , loose in the lid knuckles
+    PrintInPlace,
+    // a bore through every knuckle for a rod or a length of filament
+    Pins,
+}
+
+#[derive(Debug)]
+pub struct Case {
+    mask: Mask,
+    // the height in units of the bins it carries
+    pub depth: usize,
+    // room inside the lid above the bins
+    pub lid_depth: f64,
+    // side walls , floor and lid top
+    pub wall: f64,
+    pub hinge: Hinge,
+    // pin or bore diameter
+    pub pin: f64,
+    pub latches: usize,
+    pub handle: bool,
+}
+
+impl Case {
+    // clearance between parts that move
+    const GAP: f64 = 0.4;
+    // alternating case and lid , the case has both ends
+    const KNUCKLES: usize = 5;
+    const LATCH_WIDTH: f64 = 20.0;
+    // how far the latch tab reaches down the front of the case
+    const LATCH_LENGTH: f64 = 12.0;
+    const LATCH_THICKNESS: f64 = 2.0;
+    // how far the hook catches under the ridge
+    const HOOK: f64 = 1.5;
+    const HANDLE_WIDTH: f64 = 100.0;
+    const MIN_HANDLE: f64 = 60.0;
+    // how far the grip stands off the front
+    const HANDLE_REACH: f64 = 30.0;
+    // the grip bar is this square
+    const GRIP: f64 = 10.0;
+    const POST: f64 = 10.0;
+
+    pub fn from_mask(mask: &Mask, depth: usize) -> Self {
+        Self {
+            mask: mask.clone(),
+            depth,
+            lid_depth: 5.0,
+            wall: 3.0,
+            hinge: Hinge::PrintInPlace,
+            pin: 3.0,
+            latches: 2,
+            handle: true,
+        }
+    }
+
+    // inside the walls , the base plate footprint
+    fn inner(&self) -> DVec2 {
+        dvec2(
+            SIZE * self.mask.width() as f64,
+            SIZE * self.mask.height() as f64,
+        )
+    }
+
+    // the bins stand on the floor with their lips at the top
+    pub fn height(&self) -> f64 {
+        self.wall + total_height(self.depth)
+    }
+
+    pub fn lid_height(&self) -> f64 {
+        self.wall + self.lid_depth
+    }
+
+    fn knuckle(&self) -> f64 {
+        self.pin / 2.0 + self.wall
+    }
+
+    // the hinge axis , (y , z) , half way between the open halves so
+    // the lid lands on the case when it turns over
+    fn axis(&self) -> DVec2 {
+        let back = self.inner().y / 2.0 + self.wall;
+        dvec2(
+            back + self.knuckle() + Case::GAP,
+            (self.height() + self.lid_height()) / 2.0,
+        )
+    }
+
+    // top of the handle , below the latch tabs
+    fn handle_top(&self) -> f64 {
+        if self.latches > 0 {
+            self.height() - Case::LATCH_LENGTH - 2.0
+        } else {
+            self.height() - 2.0
+        }
+    }
+
+    fn handle_width(&self) -> f64 {
+        (self.inner().x - 2.0 * FILLET).min(Case::HANDLE_WIDTH)
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if !self.mask.is_rect() {
+            return Err("cases are rectangular".to_owned());
+        }
+        if !(1.2..=6.0).contains(&self.wall) {
+            return Err(format!("a {}mm wall is not between 1.2 and 6mm", self.wall));
+        }
+        if self.lid_depth < 0.0 {
+            return Err("the lid depth can't be negative".to_owned());
+        }
+        if self.lid_height() > self.height() {
+            return Err(format!(
+                "a {:.1}mm lid is taller than the {:.1}mm case",
+                self.lid_height(),
+                self.height()
+            ));
+        }
+        if self.pin <= 0.0 {
+            return Err("the pin diameter must be more than zero".to_owned());
+        }
+        if self.hinge == Hinge::PrintInPlace && self.pin < 2.0 {
+            return Err("printed pins thinner than 2mm snap".to_owned());
+        }
+        let span = self.inner().x - 2.0 * FILLET;
+        if span / (Case::KNUCKLES as f64) < 2.0 * self.knuckle() {
+            return Err("the case is too narrow for the hinge".to_owned());
+        }
+        if self.latches > 0 && span / (self.latches as f64) < Case::LATCH_WIDTH + 2.0 {
+            return Err(format!(
+                "{} latches don't fit across the case",
+                self.latches
+            ));
+        }
+        if self.handle {
+            if self.handle_width() < Case::MIN_HANDLE {
+                return Err("the case is too narrow for a handle".to_owned());
+            }
+            if self.handle_top() < Case::GRIP + Case::HANDLE_REACH + 1.0 {
+                return Err(format!(
+                    "a {:.1}mm case is too short for a handle under the latches",
+                    self.height()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // the case and the lid , open and side by side
+    pub fn shape(&self) -> Result<(Shape, Shape), String> {
+        self.check()?;
+        let body = cache::shape("case", self, || self.body());
+        let lid = cache::shape("case_lid", self, || self.lid());
+        Ok((body, lid))
+    }
+
+    fn body(&self) -> Shape {
+        let height = self.height();
+        let mut body: Shape = self
+            .mask
+            .outline(-self.wall, FILLET + self.wall)
+            .to_face()
+            .extrude(dvec3(0.0, 0.0, height))
+            .into();
+        let cavity: Shape = self
+            .mask
+            .outline(0.0, FILLET)
+            .translate(dvec3(0.0, 0.0, self.wall))
+            .to_face()
+            .extrude(dvec3(0.0, 0.0, height))
+            .into();
+        body = body.subtract(&cavity).into();
+        let mut plate = BasePlate::from_mask(&self.mask);
+        plate.floor = BaseFloor::new(Bottom::Solid);
+        plate.floor.thickness = self.wall;
+        body = body.union(&plate.shape()).into();
+        body = body.union(&self.knuckles(true)).into();
+        let front = -self.inner().y / 2.0 - self.wall;
+        for x in self.latch_centers() {
+            // a ridge the hook snaps under , sloped on top
+            let low = height - Case::LATCH_LENGTH + Case::HOOK + Case::GAP;
+            let ridge = section(&[
+                (front + 0.5, low),
+                (front - Case::HOOK, low),
+                (front - Case::HOOK, low + 1.0),
+                (front + 0.5, low + 1.5 + Case::HOOK),
+            ]);
+            body = body.union(&across(&ridge, x, Case::LATCH_WIDTH)).into();
+        }
+        if self.handle {
+            body = body.union(&self.grip(front)).into();
+        }
+        body
+    }
+
+    // a grip bar on two posts , the posts bevelled underneath so they
+    // print without support
+    fn grip(&self, front: f64) -> Shape {
+        let top = self.handle_top();
+        let out = front - Case::HANDLE_REACH;
+        let width = self.handle_width();
+        let post = section(&[
+            (front + 0.5, top),
+            (out, top),
+            (out, top - Case::GRIP),
+            (front + 0.5, top - Case::GRIP - Case::HANDLE_REACH - 0.5),
+        ]);
+        let bar = section(&[
+            (out, top),
+            (out + Case::GRIP, top),
+            (out + Case::GRIP, top - Case::GRIP),
+            (out, top - Case::GRIP),
+        ]);
+        let side = (width - Case::POST) / 2.0;
+        let mut grip = across(&bar, 0.0, width);
+        for x in [-side, side] {
+            grip = grip.union(&across(&post, x, Case::POST)).into();
+        }
+        grip
+    }
+
+    fn lid(&self) -> Shape {
+        let height = self.lid_height();
+        let mut lid: Shape = self
+            .mask
+            .outline(-self.wall, FILLET + self.wall)
+            .to_face()
+            .extrude(dvec3(0.0, 0.0, height))
+            .into();
+        let pocket: Shape = self
+            .mask
+            .outline(0.0, FILLET)
+            .translate(dvec3(0.0, 0.0, self.wall))
+            .to_face()
+            .extrude(dvec3(0.0, 0.0, height))
+            .into();
+        lid = lid.subtract(&pocket).into();
+        // the lid is square , so half a turn about the axis only
+        // moves it back
+        let axis = self.axis();
+        lid.set_global_translation(dvec3(0.0, 2.0 * axis.x, 0.0));
+        lid = lid.union(&self.knuckles(false)).into();
+        // a tab hanging over the front with a hook at the bottom ,
+        // joined to the lid over its full height
+        let top = self.height();
+        let front = -self.inner().y / 2.0 - self.wall;
+        let inside = front - Case::HOOK - Case::GAP;
+        let outside = inside - Case::LATCH_THICKNESS;
+        let bottom = top - Case::LATCH_LENGTH;
+        let tab = section(&[
+            (outside, bottom),
+            (front - Case::GAP, bottom),
+            (front - Case::GAP, bottom + Case::HOOK),
+            (inside, bottom + Case::HOOK),
+            (inside, top),
+            (front + 0.5, top),
+            (front + 0.5, top + height),
+            (outside, top + height),
+        ]);
+        for x in self.latch_centers() {
+            lid = lid.union(&self.flip(&tab, x, Case::LATCH_WIDTH)).into();
+        }
+        lid
+    }
+
+    // spread evenly across the front
+    fn latch_centers(&self) -> Vec<f64> {
+        let width = self.inner().x;
+        let n = self.latches as f64;
+        (0..self.latches)
+            .map(|i| width * ((i as f64 + 0.5) / n - 0.5))
+            .collect()
+    }
+
+    // the knuckles along the back , each on a leaf to its half . the
+    // case knuckles carry the pin when it is printed in place
+    fn knuckles(&self, case: bool) -> Shape {
+        let axis = self.axis();
+        let r = self.knuckle();
+        let back = self.inner().y / 2.0 + self.wall;
+        let span = self.inner().x - 2.0 * FILLET;
+        let length = span / Case::KNUCKLES as f64;
+        let pin = self.pin / 2.0;
+        let bore = match (self.hinge, case) {
+            (Hinge::PrintInPlace, true) => None,
+            (Hinge::PrintInPlace, false) => Some(pin + Case::GAP),
+            (Hinge::Pins, _) => Some(pin),
+        };
+        let leaf = if case {
+            section(&[
+                (back - 0.5, axis.y - r),
+                (axis.x, axis.y - r),
+                (axis.x, self.height()),
+                (back - 0.5, self.height()),
+            ])
+        } else {
+            // the lid back , open , is as far past the axis as the
+            // case back is short of it
+            let lid_back = 2.0 * axis.x - back;
+            section(&[
+                (axis.x, 0.0),
+                (lid_back + 0.5, 0.0),
+                (lid_back + 0.5, self.lid_height()),
+                (lid_back, self.lid_height()),
+                (lid_back, axis.y),
+                (axis.x, axis.y),
+            ])
+        };
+        let mut knuckles: Option<Shape> = None;
+        for i in 0..Case::KNUCKLES {
+            // even knuckles are on the case
+            if (i % 2 == 0) != case {
+                continue;
+            }
+            let x = -span / 2.0 + (i as f64 + 0.5) * length;
+            let width = length - Case::GAP;
+            let mut knuckle: Shape = across(&round(axis, r), x, width);
+            knuckle = knuckle.union(&across(&leaf, x, width)).into();
+            if let Some(bore) = bore {
+                knuckle = knuckle
+                    .subtract(&across(&round(axis, bore), x, width + 2.0))
+                    .into();
+            }
+            knuckles = Some(match knuckles {
+                Some(k) => k.union(&knuckle).into(),
+                None => knuckle,
+            });
+        }
+        let mut knuckles = knuckles.unwrap();
+        if bore.is_none() {
+            // from the first case knuckle to the last , through the lid ones
+            knuckles = knuckles
+                .union(&across(&round(axis, pin), 0.0, span - Case::GAP))
+                .into();
+        }
+        knuckles
+    }
+
+    // a lid part drawn closed , turned half a turn about the hinge
+    // axis to where it is when open
+    fn flip(&self, section: &Outline, x: f64, width: f64) -> Shape {
+        let axis = self.axis();
+        let origin = dvec3(x - width / 2.0, 2.0 * axis.x, 2.0 * axis.y);
+        section.prism(origin, -DVec3::Y, -DVec3::Z, dvec3(width, 0.0, 0.0))
+    }
+}
+
+// a section across the case , (y , z) pairs
+fn section(points: &[(f64, f64)]) -> Outline {
+    Outline::new(points.iter().map(|(y, z)| dvec2(*y, *z)).collect()).unwrap()
+}
+
+// a circle in the (y , z) plane
+fn round(center: DVec2, radius: f64) -> Outline {
+    let points = (0..48)
+        .map(|i| center + radius * DVec2::from_angle(i as f64 * std::f64::consts::TAU / 48.0))
+        .collect();
+    Outline::new(points).unwrap()
+}
+
+// a section pushed along x , `width` wide about `x`
+fn across(section: &Outline, x: f64, width: f64) -> Shape {
+    let origin = dvec3(x - width / 2.0, 0.0, 0.0);
+    section.prism(origin, DVec3::Y, DVec3::Z, dvec3(width, 0.0, 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(width: usize, length: usize, depth: usize) -> Case {
+        Case::from_mask(&Mask::rect(width, length), depth)
+    }
+
+    #[test]
+    fn closing_puts_the_lid_on_the_case() {
+        let case = case(3, 2, 6);
+        let axis = case.axis();
+        // half a turn about the axis takes the lid from the bed onto
+        // the top of the walls
+        assert!((2.0 * axis.y - case.lid_height() - case.height()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn knuckles_clear_the_walls() {
+        for pin in [2.0, 3.0, 6.0] {
+            let mut case = case(3, 2, 6);
+            case.pin = pin;
+            let axis = case.axis();
+            let back = case.inner().y / 2.0 + case.wall;
+            // the knuckle turns without touching the case back , or the
+            // lid back once it is closed
+            assert!(axis.x - case.knuckle() >= back + Case::GAP - 1e-9);
+            let lid_back = 2.0 * axis.x - back;
+            assert!(axis.x + case.knuckle() <= lid_back - Case::GAP + 1e-9);
+            // and clears the floor of each open half
+            assert!(axis.y - case.knuckle() > 0.0);
+        }
+    }
+
+    #[test]
+    fn hinges_need_room() {
+        let mut case = case(3, 2, 6);
+        assert!(case.check().is_ok());
+        case.pin = 1.5;
+        assert_eq!(
+            case.check().unwrap_err(),
+            "printed pins thinner than 2mm snap"
+        );
+        case.hinge = Hinge::Pins;
+        assert!(case.check().is_ok());
+        case.pin = 0.0;
+        assert!(case.check().is_err());
+        // five knuckles across one cell
+        let mut narrow = self::case(1, 2, 6);
+        narrow.handle = false;
+        narrow.latches = 1;
+        narrow.pin = 8.0;
+        assert_eq!(
+            narrow.check().unwrap_err(),
+            "the case is too narrow for the hinge"
+        );
+    }
+
+    #[test]
+    fn latches_spread_across_the_front() {
+        let mut case = case(4, 2, 6);
+        assert_eq!(case.latch_centers(), [-SIZE, SIZE]);
+        case.latches = 3;
+        let centers = case.latch_centers();
+        assert_eq!(centers.len(), 3);
+        assert!(centers[1].abs() < 1e-9);
+        assert!((centers[0] + centers[2]).abs() < 1e-9);
+        case.latches = 8;
+        assert_eq!(
+            case.check().unwrap_err(),
+            "8 latches don't fit across the case"
+        );
+    }
+
+    #[test]
+    fn handles_sit_under_the_latches() {
+        let mut case = case(3, 2, 6);
+        assert_eq!(case.handle_width(), Case::HANDLE_WIDTH);
+        assert!(case.handle_top() < case.height() - Case::LATCH_LENGTH);
+        case.latches = 0;
+        assert!(case.handle_top() > case.height() - Case::LATCH_LENGTH);
+        // too short to hang a grip under the latches
+        let mut short = self::case(3, 2, 2);
+        assert!(short.check().is_err());
+        short.handle = false;
+        assert!(short.check().is_ok());
+        // too narrow for a hand , with a hinge small enough to fit
+        let mut thin = self::case(1, 2, 6);
+        thin.latches = 1;
+        thin.wall = 1.2;
+        thin.pin = 2.0;
+        assert_eq!(
+            thin.check().unwrap_err(),
+            "the case is too narrow for a handle"
+        );
+    }
+
+    #[test]
+    fn cases_are_rectangular() {
+        let ell = Mask::from_ascii("#.\n##\n").unwrap();
+        let case = Case::from_mask(&ell, 6);
+        assert_eq!(case.check().unwrap_err(), "cases are rectangular");
+        let mut thin = self::case(3, 2, 6);
+        thin.wall = 1.0;
+        assert!(thin.check().is_err());
+        // a lid deeper than the case
+        thin.wall = 3.0;
+        thin.lid_depth = 100.0;
+        assert!(thin.check().is_err());
+    }
+}
@@ -0,0 +1,268 @@
+// Matrix codes
+// a qr code from a string , the dark modules are square pixels
+// raised from or cut into the floor or the underside of the bin ,
+// for scanning bins into an inventory
+
+use glam::{dvec2, dvec3, DVec2, DVec3};
+use opencascade::primitives::Shape;
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::gridfin::{Connector, Walls, MAGNET_DIAMETER, MID_LIFT, V_UNIT};
+use crate::mask::Mask;
+use crate::outline::Outline;
+use crate::text::Place;
+
+#[derive(Debug)]
+pub struct Code {
+    pub text: String,
+    // side of one module in mm
+    pub module: f64,
+    pub depth: f64,
+    pub place: Place,
+    // offset from the middle of the floor in mm
+    pub pos: DVec2,
+    // raised modules , cut into the bin if false
+    pub emboss: bool,
+}
+
+impl Code {
+    // a nozzle can't print a smaller square
+    const MIN_MODULE: f64 = 0.4;
+    // keep this much floor under an engraving
+    const MIN_FLOOR: f64 = 0.6;
+    // magnet holes are this deep
+    const MAGNET_DEPTH: f64 = 2.0;
+
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_owned(),
+            module: 1.0,
+            depth: 0.6,
+            place: Place::Floor,
+            pos: dvec2(0.0, 0.0),
+            emboss: false,
+        }
+    }
+
+    // dark modules , row by row from the top
+    fn modules(&self) -> Result<(usize, Vec<bool>), String> {
+        let code = QrCode::with_error_correction_level(&self.text, EcLevel::M)
+            .map_err(|e| format!("{:?} : {}", self.text, e))?;
+        let dark = code
+            .to_colors()
+            .into_iter()
+            .map(|c| c == Color::Dark)
+            .collect();
+        Ok((code.width(), dark))
+    }
+
+    // the side of the whole code in mm
+    pub fn size(&self) -> Result<f64, String> {
+        let (width, _) = self.modules()?;
+        Ok(width as f64 * self.module)
+    }
+
+    // the middle of the code , on the floor or under the middle cell
+    fn center(&self, mask: &Mask) -> DVec2 {
+        match self.place {
+            Place::Bottom => {
+                let (x, y) = mask
+                    .cells()
+                    .min_by(|a, b| {
+                        let da = mask.cell_center(a.0, a.1).length();
+                        let db = mask.cell_center(b.0, b.1).length();
+                        da.total_cmp(&db)
+                    })
+                    .unwrap();
+                mask.cell_center(x, y) + self.pos
+            }
+            _ => self.pos,
+        }
+    }
+
+    pub fn check(&self, mask: &Mask, walls: &Walls) -> Result<(), String> {
+        if self.module < Code::MIN_MODULE {
+            return Err(format!(
+                "modules smaller than {}mm don't print",
+                Code::MIN_MODULE
+            ));
+        }
+        if self.depth <= 0.0 {
+            return Err("code depth must be more than zero".to_owned());
+        }
+        let half = self.size()? / 2.0;
+        let center = self.center(mask);
+        match self.place {
+            Place::Floor => {
+                // the whole square clear of the walls , not just its corners
+                let corner = DVec2::splat(half);
+                if !mask.holds(walls.thickness, center - corner, center + corner) {
+                    return Err(format!(
+                        "a {:.1}mm code does not fit on the floor",
+                        2.0 * half
+                    ));
+                }
+                let floor = V_UNIT - MID_LIFT + walls.floor;
+                if !self.emboss && self.depth > floor - Code::MIN_FLOOR {
+                    return Err(format!(
+                        "engraving {}mm deep goes through a {:.2}mm floor",
+                        self.depth, floor
+                    ));
+                }
+            }
+            Place::Bottom => {
+                if self.emboss {
+                    return Err("codes under the bin can only be engraved".to_owned());
+                }
+                // inside the magnet holes
+                let clear = Connector::magnets()[0].x - MAGNET_DIAMETER / 2.0;
+                if half + self.pos.x.abs().max(self.pos.y.abs()) > clear {
+                    return Err(format!(
+                        "a {:.1}mm code runs into the magnets under the bin",
+                        2.0 * half
+                    ));
+                }
+                if self.depth >= Code::MAGNET_DEPTH {
+                    return Err(format!(
+                        "engravings under the bin must be less than {}mm deep",
+                        Code::MAGNET_DEPTH
+                    ));
+                }
+            }
+            Place::Front | Place::Shelf => {
+                return Err("codes go on the floor or the bottom".to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    // the module solid for a bin , to be unioned when embossed or
+    // subtracted when engraved
+    pub fn shape(&self, mask: &Mask, walls: &Walls) -> Result<Shape, String> {
+        self.check(mask, walls)?;
+        let (width, dark) = self.modules()?;
+        let half = width as f64 * self.module / 2.0;
+        let center = self.center(mask);
+        // engraved modules start a little proud so the faces don't touch
+        let (lift, depth) = if self.emboss {
+            (0.0, self.depth)
+        } else {
+            (0.1, self.depth + 0.1)
+        };
+        let (origin, u, through) = match self.place {
+            Place::Bottom => {
+                // mirrored in x so it reads from underneath
+                let origin = dvec3(center.x + half, center.y - half, -lift);
+                (origin, -DVec3::X, depth * DVec3::Z)
+            }
+            _ => {
+                let origin = dvec3(center.x - half, center.y - half, walls.bottom() + lift);
+                let through = if self.emboss { DVec3::Z } else { -DVec3::Z };
+                (origin, DVec3::X, depth * through)
+            }
+        };
+        // runs of dark modules along each row , so there are fewer
+        // solids to join
+        let mut solid: Option<Shape> = None;
+        for row in 0..width {
+            // rows count down from the top
+            let y = (width - 1 - row) as f64 * self.module;
+            let mut col = 0;
+            while col < width {
+                if !dark[row * width + col] {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < width && dark[row * width + col] {
+                    col += 1;
+                }
+                let (x0, x1) = (start as f64 * self.module, col as f64 * self.module);
+                let run = Outline::new(vec![
+                    dvec2(x0, y),
+                    dvec2(x1, y),
+                    dvec2(x1, y + self.module),
+                    dvec2(x0, y + self.module),
+                ])?;
+                let prism = run.prism(origin, u, DVec3::Y, through);
+                solid = Some(match solid {
+                    Some(s) => s.union(&prism).into(),
+                    None => prism,
+                });
+            }
+        }
+        solid.ok_or(format!("no modules in the code for {:?}", self.text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a version 1 code , 21 modules across
+    fn code(place: Place, module: f64, pos: DVec2) -> Code {
+        let mut code = Code::new("A1");
+        code.place = place;
+        code.module = module;
+        code.pos = pos;
+        code
+    }
+
+    #[test]
+    fn codes_are_whole_modules() {
+        assert_eq!(code(Place::Floor, 1.0, DVec2::ZERO).size(), Ok(21.0));
+        assert_eq!(code(Place::Floor, 0.5, DVec2::ZERO).size(), Ok(10.5));
+    }
+
+    #[test]
+    fn codes_must_not_straddle_a_notch() {
+        let walls = Walls::default();
+        // a 52.5mm code in the middle of a U , every corner is on the
+        // floor but the top edge runs across the notch
+        let wide = code(Place::Floor, 2.5, DVec2::ZERO);
+        let u = Mask::from_ascii("#.#\n###\n").unwrap();
+        assert!(wide.check(&u, &walls).is_err());
+        assert!(wide.check(&Mask::rect(3, 2), &walls).is_ok());
+        // down in the bottom row it fits
+        let low = code(Place::Floor, 1.0, dvec2(0.0, -21.0));
+        assert!(low.check(&u, &walls).is_ok());
+        // and in the arm of an L
+        let ell = Mask::from_ascii("#.\n##\n").unwrap();
+        assert!(code(Place::Floor, 1.0, dvec2(-21.0, 21.0))
+            .check(&ell, &walls)
+            .is_ok());
+        assert!(code(Place::Floor, 1.0, dvec2(21.0, 21.0))
+            .check(&ell, &walls)
+            .is_err());
+    }
+
+    #[test]
+    fn engravings_leave_floor() {
+        let walls = Walls::default();
+        let mask = Mask::rect(2, 2);
+        let mut deep = code(Place::Floor, 1.0, DVec2::ZERO);
+        deep.depth = 5.0;
+        assert!(deep.check(&mask, &walls).is_err());
+        deep.emboss = true;
+        assert!(deep.check(&mask, &walls).is_ok());
+    }
+
+    #[test]
+    fn codes_under_the_bin_miss_the_magnets() {
+        let walls = Walls::default();
+        let mask = Mask::rect(2, 2);
+        assert!(code(Place::Bottom, 0.9, DVec2::ZERO)
+            .check(&mask, &walls)
+            .is_ok());
+        let err = code(Place::Bottom, 1.0, DVec2::ZERO)
+            .check(&mask, &walls)
+            .unwrap_err();
+        assert!(err.contains("magnets"), "{}", err);
+        let mut raised = code(Place::Bottom, 0.9, DVec2::ZERO);
+        raised.emboss = true;
+        assert!(raised.check(&mask, &walls).is_err());
+        assert!(code(Place::Front, 1.0, DVec2::ZERO)
+            .check(&mask, &walls)
+            .is_err());
+    }
+}
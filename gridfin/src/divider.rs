@@ -0,0 +1,305 @@
+// Removable dividers
+// slots cut into the inner walls and the thin plates that slide into them ,
+// so compartments can be moved after printing
+
+use glam::{dvec2, dvec3};
+use opencascade::{primitives::Shape, workplane::Workplane};
+
+use crate::gridfin::{Walls, SIZE, V_UNIT};
+use crate::mask::Mask;
+use crate::outline::Outline;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    // a plate across the bin at an x position , running along y
+    X,
+    // a plate across the bin at a y position , running along x
+    Y,
+}
+
+#[derive(Debug, Clone)]
+pub struct Divider {
+    pub thickness: f64,
+    // gap between the plate and the slot
+    pub clearance: f64,
+    // how far the slot goes into the wall
+    pub slot_depth: f64,
+    // positions in mm from the bin center
+    pub slots: Vec<(Axis, f64)>,
+}
+
+impl Divider {
+    // leave this much wall behind the slot
+    const MIN_WALL: f64 = 0.8;
+    // a shallower slot doesn't hold the plate
+    const MIN_SLOT: f64 = 0.4;
+    // the deepest slot the walls get unless asked
+    const SLOT_DEPTH: f64 = 1.0;
+    const FILLET_STEPS: usize = 6;
+
+    pub fn new(slots: Vec<(Axis, f64)>) -> Self {
+        Self {
+            thickness: 1.2,
+            clearance: 0.3,
+            slot_depth: Divider::SLOT_DEPTH,
+            slots,
+        }
+    }
+
+    // as deep as the walls allow , up to the usual depth
+    pub fn slot_depth_for(walls: &Walls) -> f64 {
+        (walls.thickness - Divider::MIN_WALL).min(Divider::SLOT_DEPTH)
+    }
+
+    pub fn check(&self, mask: &Mask, walls: &Walls) -> Result<(), String> {
+        if self.slot_depth < Divider::MIN_SLOT {
+            return Err(format!(
+                "a {:.2}mm slot doesn't hold a plate , slots need {}mm with {}mm of wall behind",
+                self.slot_depth,
+                Divider::MIN_SLOT,
+                Divider::MIN_WALL
+            ));
+        }
+        if self.slot_depth > walls.thickness - Divider::MIN_WALL {
+            return Err(format!(
+                "slot depth {} leaves less than {}mm of wall",
+                self.slot_depth,
+                Divider::MIN_WALL
+            ));
+        }
+        if self.thickness <= 0.0 {
+            return Err("divider thickness must be more than zero".to_owned());
+        }
+        for (axis, pos) in &self.slots {
+            self.check_slot(mask, walls, *axis, *pos)?;
+        }
+        Ok(())
+    }
+
+    // the plate has to cross open floor , clear of the walls and the
+    // corner fillets beside it along its whole length
+    fn check_slot(&self, mask: &Mask, walls: &Walls, axis: Axis, pos: f64) -> Result<(), String> {
+        let name = match axis {
+            Axis::X => "x",
+            Axis::Y => "y",
+        };
+        let spans = self.spans(mask, walls, axis, pos);
+        if spans.is_empty() {
+            return Err(format!("slot at {} {} is outside the bin", name, pos));
+        }
+        let margin =
+            walls.thickness + walls.inner_fillet() + (self.thickness + self.clearance) / 2.0;
+        for (lo, hi) in spans {
+            if hi - lo <= 0.0 {
+                return Err(format!("slot at {} {} has no room for a plate", name, pos));
+            }
+            let steps = (hi - lo).ceil() as usize;
+            for i in 0..=steps {
+                let along = lo + (hi - lo) * i as f64 / steps as f64;
+                let clear = [-margin, margin].iter().all(|side| {
+                    let p = match axis {
+                        Axis::X => dvec2(pos + side, along),
+                        Axis::Y => dvec2(along, pos + side),
+                    };
+                    mask.contains(p)
+                });
+                if !clear {
+                    return Err(format!(
+                        "slot at {} {} runs into a wall , keep it {:.2}mm in from the outside",
+                        name, pos, margin
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the runs of the bin interior crossed by a slot line ,
+    // start and end in mm along the plate , between the inner walls
+    pub fn spans(&self, mask: &Mask, walls: &Walls, axis: Axis, pos: f64) -> Vec<(f64, f64)> {
+        let (cells, across) = match axis {
+            Axis::X => (mask.height(), mask.width()),
+            Axis::Y => (mask.width(), mask.height()),
+        };
+        let line = pos / SIZE + across as f64 / 2.0;
+        if line < 0.0 || line >= across as f64 {
+            return Vec::new();
+        }
+        let line = line as usize;
+        let occupied = |i: usize| match axis {
+            Axis::X => mask.occupied(line, i),
+            Axis::Y => mask.occupied(i, line),
+        };
+        let mut spans = Vec::new();
+        let mut start = None;
+        for i in 0..=cells {
+            match (start, i < cells && occupied(i)) {
+                (None, true) => start = Some(i),
+                (Some(s), false) => {
+                    let lo = SIZE * (s as f64 - cells as f64 / 2.0) + walls.thickness;
+                    let hi = SIZE * (i as f64 - cells as f64 / 2.0) - walls.thickness;
+                    spans.push((lo, hi));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        spans
+    }
+
+    // a box on the xy plane in plate coordinates , turned to suit the axis
+    fn slot_box(&self, axis: Axis, pos: f64, along: f64, bottom: f64, height: f64) -> Shape {
+        let width = self.thickness + self.clearance;
+        let length = 2.0 * self.slot_depth;
+        let (center, w, l) = match axis {
+            Axis::X => (dvec2(pos, along), width, length),
+            Axis::Y => (dvec2(along, pos), length, width),
+        };
+        let rect = Workplane::xy()
+            .rect(w, l)
+            .translate(dvec3(center.x, center.y, bottom));
+        rect.to_face().extrude(dvec3(0.0, 0.0, height)).into()
+    }
+
+    // all the slots for a bin of `height` units , up through the lip
+    pub fn slots(&self, mask: &Mask, walls: &Walls, height: usize) -> Option<Shape> {
+        let top = V_UNIT * (height + 2) as f64;
+        let bottom = walls.bottom();
+        let mut cutter: Option<Shape> = None;
+        for (axis, pos) in &self.slots {
+            for (lo, hi) in self.spans(mask, walls, *axis, *pos) {
+                for along in [lo, hi] {
+                    let slot = self.slot_box(*axis, *pos, along, bottom, top - bottom);
+                    cutter = Some(match cutter {
+                        Some(c) => c.union(&slot).into(),
+                        None => slot,
+                    });
+                }
+            }
+        }
+        cutter
+    }
+
+    // a flat plate , width along x and height along y , with the
+    // bottom corners rounded by `r` to clear the fillet in the cavity
+    fn plate(&self, width: f64, height: f64, r: f64) -> Shape {
+        let corners = [
+            (dvec2(-width / 2.0 + r, r), std::f64::consts::PI),
+            (dvec2(width / 2.0 - r, r), 1.5 * std::f64::consts::PI),
+        ];
+        let mut points = Vec::new();
+        for (center, start) in corners {
+            // square corners when the cavity has no fillet
+            if r <= 0.0 {
+                points.push(center);
+                continue;
+            }
+            for i in 0..=Divider::FILLET_STEPS {
+                let a = start + std::f64::consts::FRAC_PI_2 * i as f64 / Divider::FILLET_STEPS as f64;
+                points.push(center + r * dvec2(a.cos(), a.sin()));
+            }
+        }
+        points.push(dvec2(width / 2.0, height));
+        points.push(dvec2(-width / 2.0, height));
+        let outline = Outline::new(points).unwrap();
+        outline.face().extrude(dvec3(0.0, 0.0, self.thickness)).into()
+    }
+
+    // the plates that fit the slots , one of each length
+    pub fn plates(&self, mask: &Mask, walls: &Walls, height: usize) -> Vec<(f64, Shape)> {
+        let plate_height = walls.depth(height) - self.clearance;
+        let mut lengths: Vec<f64> = Vec::new();
+        for (axis, pos) in &self.slots {
+            for (lo, hi) in self.spans(mask, walls, *axis, *pos) {
+                let length = hi - lo + 2.0 * self.slot_depth - self.clearance;
+                if !lengths.iter().any(|l| (l - length).abs() < 0.01) {
+                    lengths.push(length);
+                }
+            }
+        }
+        lengths
+            .into_iter()
+            .map(|length| {
+                let plate = self.plate(length, plate_height, walls.cavity_fillet);
+                (length, plate)
+            })
+            .collect()
+    }
+}
+
+// lets the mm positions be turned into slot lists
+pub fn positions(axis: Axis, values: &[f64]) -> Vec<(Axis, f64)> {
+    values.iter().map(|v| (axis, *v)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_keep_their_axis() {
+        assert_eq!(
+            positions(Axis::Y, &[-10.0, 5.0]),
+            [(Axis::Y, -10.0), (Axis::Y, 5.0)]
+        );
+    }
+
+    #[test]
+    fn slot_depth_follows_the_walls() {
+        let mut walls = Walls::default();
+        assert_eq!(Divider::slot_depth_for(&walls), 1.0);
+        walls.thickness = 1.5;
+        assert!((Divider::slot_depth_for(&walls) - 0.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn spans_run_between_the_walls() {
+        let walls = Walls::default();
+        let inside = SIZE / 2.0 - walls.thickness;
+        let d = Divider::new(Vec::new());
+        let mask = Mask::rect(2, 1);
+        assert_eq!(d.spans(&mask, &walls, Axis::X, 0.0), [(-inside, inside)]);
+        assert_eq!(
+            d.spans(&mask, &walls, Axis::Y, 0.0),
+            [(-SIZE + walls.thickness, SIZE - walls.thickness)]
+        );
+        assert!(d.spans(&mask, &walls, Axis::X, 50.0).is_empty());
+        // the short arm of an L
+        let ell = Mask::from_ascii("#.\n##\n").unwrap();
+        assert_eq!(
+            d.spans(&ell, &walls, Axis::X, SIZE / 2.0),
+            [(-SIZE + walls.thickness, -walls.thickness)]
+        );
+    }
+
+    // one slot in a bin with the usual walls
+    fn check(mask: &Mask, axis: Axis, pos: f64) -> Result<(), String> {
+        Divider::new(vec![(axis, pos)]).check(mask, &Walls::default())
+    }
+
+    #[test]
+    fn slots_must_cross_open_floor() {
+        let mask = Mask::rect(2, 1);
+        assert!(check(&mask, Axis::X, 0.0).is_ok());
+        let err = check(&mask, Axis::X, 39.0).unwrap_err();
+        assert!(err.contains("runs into a wall"), "{}", err);
+        let err = check(&mask, Axis::X, 100.0).unwrap_err();
+        assert_eq!(err, "slot at x 100 is outside the bin");
+        // along the foot of an L , right under the notch
+        let ell = Mask::from_ascii("#.\n##\n").unwrap();
+        assert!(check(&ell, Axis::Y, -2.0).is_err());
+        assert!(check(&ell, Axis::Y, -20.0).is_ok());
+    }
+
+    #[test]
+    fn slots_leave_wall_behind() {
+        let mut walls = Walls::default();
+        let mask = Mask::rect(1, 1);
+        let mut d = Divider::new(vec![(Axis::X, 0.0)]);
+        d.slot_depth = 1.5;
+        assert!(d.check(&mask, &walls).is_err());
+        walls.thickness = 1.0;
+        d.slot_depth = Divider::slot_depth_for(&walls);
+        assert!(d.check(&mask, &walls).is_err());
+    }
+}
@@ -0,0 +1,500 @@
+// Dimensioned drawings
+// a top view , a front view and a side view as svg at full scale , drawn from the
+// parameters of the part rather than from the model , with the sizes ,
+// compartments and holes called out
+
+use std::fs;
+
+use glam::{dvec2, DVec2};
+
+use crate::divider::{Axis, Divider};
+use crate::gridfin::{total_height, Connector, Walls, MAGNET_DIAMETER, MID_LIFT, SIZE, V_UNIT};
+use crate::mask::Mask;
+
+// space around each view for the dimensions in mm
+const MARGIN: f64 = 25.0;
+const TEXT_SIZE: f64 = 3.5;
+const ROW: f64 = 5.0;
+
+// a round feature seen from the top
+#[derive(Debug, Clone)]
+pub struct Hole {
+    pub kind: String,
+    pub center: DVec2,
+    pub diameter: f64,
+}
+
+impl Hole {
+    pub fn new(kind: &str, center: DVec2, diameter: f64) -> Self {
+        Self {
+            kind: kind.to_owned(),
+            center,
+            diameter,
+        }
+    }
+}
+
+pub struct Drawing {
+    mask: Mask,
+    // bin height in units , none for a base plate
+    height: Option<usize>,
+    pub title: String,
+    // magnet holes under the feet
+    pub magnets: bool,
+    pub divider: Option<Divider>,
+    // wall and floor sizes of the cavity , the shell of a lite bin
+    pub walls: Walls,
+    // an open cavity inside the walls , filled bins have none
+    pub open: bool,
+    // floor under the sockets of a base plate
+    pub base_floor: f64,
+    pub holes: Vec<Hole>,
+}
+
+// svg text in mm , y down
+struct Sheet {
+    svg: String,
+}
+
+impl Sheet {
+    fn line(&mut self, a: DVec2, b: DVec2, class: &str) {
+        self.svg.push_str(&format!(
+            "  <line class=\"{}\" x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\"/>\n",
+            class, a.x, a.y, b.x, b.y
+        ));
+    }
+
+    fn polygon(&mut self, points: &[DVec2], class: &str) {
+        let points: Vec<String> = points
+            .iter()
+            .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+            .collect();
+        self.svg.push_str(&format!(
+            "  <polygon class=\"{}\" points=\"{}\"/>\n",
+            class,
+            points.join(" ")
+        ));
+    }
+
+    fn circle(&mut self, center: DVec2, radius: f64, class: &str) {
+        self.svg.push_str(&format!(
+            "  <circle class=\"{}\" cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\"/>\n",
+            class, center.x, center.y, radius
+        ));
+    }
+
+    fn text(&mut self, pos: DVec2, angle: f64, anchor: &str, text: &str) {
+        self.svg.push_str(&format!(
+            "  <text x=\"{:.2}\" y=\"{:.2}\" text-anchor=\"{}\" transform=\"rotate({:.1} {:.2} {:.2})\">{}</text>\n",
+            pos.x,
+            pos.y,
+            anchor,
+            angle,
+            pos.x,
+            pos.y,
+            escape(text)
+        ));
+    }
+
+    // a dimension from `a` to `b` , the line set out by `offset`
+    // to the right of the direction from a to b on the page
+    fn dimension(&mut self, a: DVec2, b: DVec2, offset: f64, text: &str) {
+        let dir = (b - a).normalize();
+        let out = dir.perp();
+        let side = offset.signum();
+        // extension lines start a little clear of the part
+        self.line(a + out * side, a + out * (offset + side * 1.5), "thin");
+        self.line(b + out * side, b + out * (offset + side * 1.5), "thin");
+        let (a1, b1) = (a + out * offset, b + out * offset);
+        self.svg.push_str(&format!(
+            "  <line class=\"dim\" x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" marker-start=\"url(#arrow)\" marker-end=\"url(#arrow)\"/>\n",
+            a1.x, a1.y, b1.x, b1.y
+        ));
+        // keep the text reading left to right or bottom to top
+        let mut angle = dir.y.atan2(dir.x).to_degrees();
+        if angle > 90.0 - 1e-6 {
+            angle -= 180.0;
+        } else if angle <= -90.0 - 1e-6 {
+            angle += 180.0;
+        }
+        let up = -dvec2(0.0, 1.0).rotate(DVec2::from_angle(angle.to_radians()));
+        let mid = (a + b) / 2.0 + out * offset + up * 1.0;
+        self.text(mid, angle, "middle", text);
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn mm(value: f64) -> String {
+    format!("{:.1}", value)
+}
+
+impl Drawing {
+    pub fn new(mask: &Mask, height: Option<usize>) -> Self {
+        Self {
+            mask: mask.clone(),
+            height,
+            title: String::new(),
+            magnets: height.is_some(),
+            divider: None,
+            walls: Walls::default(),
+            open: height.is_some(),
+            base_floor: 0.0,
+            holes: Vec::new(),
+        }
+    }
+
+    // overall height in mm , lip included
+    fn total(&self) -> f64 {
+        match self.height {
+            Some(h) => total_height(h),
+            None => MID_LIFT + self.base_floor,
+        }
+    }
+
+    // the magnet holes under the feet
+    fn magnet_holes(&self) -> Vec<DVec2> {
+        if !self.magnets {
+            return Vec::new();
+        }
+        self.mask
+            .cells()
+            .flat_map(|(x, y)| {
+                let cell = self.mask.cell_center(x, y);
+                Connector::magnets().map(|pos| cell + pos)
+            })
+            .collect()
+    }
+
+    // the runs inside the walls along a line across the top view ,
+    // along x at y = `at` or along y at x = `at`
+    fn runs(&self, axis: Axis, at: f64) -> Vec<(f64, f64)> {
+        let inner = self.mask.polygon(self.walls.thickness);
+        let n = inner.len();
+        let mut cuts: Vec<f64> = (0..n)
+            .filter_map(|i| {
+                let (a, b) = (inner[i], inner[(i + 1) % n]);
+                // along and across the line
+                let (a, b) = match axis {
+                    Axis::X => (a, b),
+                    Axis::Y => (dvec2(a.y, a.x), dvec2(b.y, b.x)),
+                };
+                ((a.y > at) != (b.y > at)).then(|| a.x + (at - a.y) * (b.x - a.x) / (b.y - a.y))
+            })
+            .collect();
+        cuts.sort_by(f64::total_cmp);
+        cuts.chunks(2).map(|c| (c[0], c[1])).collect()
+    }
+
+    // the compartments along one axis between the inner walls and the
+    // slots , across the back row of cells for x and the left column for y
+    fn compartments(&self, axis: Axis) -> Vec<(f64, f64)> {
+        let at = match axis {
+            Axis::X => SIZE * (self.mask.height() - 1) as f64 / 2.0,
+            Axis::Y => -SIZE * (self.mask.width() - 1) as f64 / 2.0,
+        };
+        let slots: Vec<f64> = match &self.divider {
+            Some(divider) => divider
+                .slots
+                .iter()
+                .filter(|(a, _)| *a == axis)
+                .map(|(_, pos)| *pos)
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut compartments = Vec::new();
+        for (lo, hi) in self.runs(axis, at) {
+            let mut stops = vec![lo, hi];
+            stops.extend(slots.iter().filter(|p| **p > lo && **p < hi));
+            stops.sort_by(f64::total_cmp);
+            compartments.extend(stops.windows(2).map(|w| (w[0], w[1])));
+        }
+        compartments
+    }
+
+    // the outside of a bin or plate seen from the front or the side ,
+    // `span` wide with the levels , the floor and the slots across it
+    fn elevation(
+        &self,
+        sheet: &mut Sheet,
+        view: &dyn Fn(f64, f64) -> DVec2,
+        span: f64,
+        slots: Axis,
+    ) {
+        let total = self.total();
+        let mut levels = vec![0.0, total];
+        if let Some(h) = self.height {
+            levels.insert(1, V_UNIT);
+            levels.insert(2, V_UNIT * (h + 1) as f64);
+        } else if self.base_floor > 0.0 {
+            levels.insert(1, self.base_floor);
+        }
+        let half = span / 2.0;
+        let body = [
+            view(-half, 0.0),
+            view(half, 0.0),
+            view(half, total),
+            view(-half, total),
+        ];
+        sheet.polygon(&body, "");
+        for z in &levels[1..levels.len() - 1] {
+            sheet.line(view(-half, *z), view(half, *z), "thin");
+        }
+        // a thicker floor is inside the part
+        if self.height.is_some() && self.open && self.walls.floor > 0.0 {
+            let z = self.walls.bottom();
+            let inner = half - self.walls.thickness;
+            sheet.line(view(-inner, z), view(inner, z), "thin hidden");
+        }
+        if let (Some(h), Some(divider)) = (self.height, &self.divider) {
+            for (_, pos) in divider.slots.iter().filter(|(a, _)| *a == slots) {
+                let z = V_UNIT * (h + 1) as f64;
+                sheet.line(view(*pos, self.walls.bottom()), view(*pos, z), "slot");
+            }
+        }
+    }
+
+    pub fn svg(&self) -> String {
+        let width = SIZE * self.mask.width() as f64;
+        let depth = SIZE * self.mask.height() as f64;
+        let total = self.total();
+        let magnets = self.magnet_holes();
+        let page_width = width + depth + 3.5 * MARGIN;
+        let page_height = 3.5 * MARGIN + depth + total + ROW * (self.holes.len() + 3) as f64;
+        // the view centers on the page , the front view sits under the top
+        // view and the side view , from the right , next to the front view
+        let top_origin = dvec2(MARGIN + width / 2.0, MARGIN + depth / 2.0);
+        let front_origin = dvec2(MARGIN + width / 2.0, 2.5 * MARGIN + depth + total);
+        let side_origin = dvec2(2.5 * MARGIN + width + depth / 2.0, front_origin.y);
+        let top = |p: DVec2| top_origin + dvec2(p.x, -p.y);
+        let front = |x: f64, z: f64| front_origin + dvec2(x, -z);
+        let side = |y: f64, z: f64| side_origin + dvec2(y, -z);
+
+        let mut sheet = Sheet { svg: String::new() };
+        sheet.svg.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.1}mm\" height=\"{1:.1}mm\" viewBox=\"0 0 {0:.1} {1:.1}\">\n",
+            page_width, page_height
+        ));
+        sheet.svg.push_str(&format!(
+            "  <style>\n    line, polygon, circle {{ fill: none; stroke: black; stroke-width: 0.35 }}\n    .thin, .dim {{ stroke-width: 0.18 }}\n    .hidden {{ stroke-dasharray: 1.5 1 }}\n    .slot {{ stroke: #c03030; stroke-width: 0.5; stroke-dasharray: 3 1 }}\n    .grid {{ stroke: #b0b0b0; stroke-width: 0.18 }}\n    text {{ font-family: sans-serif; font-size: {}px }}\n  </style>\n",
+            TEXT_SIZE
+        ));
+        sheet.svg.push_str("  <defs>\n    <marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"8\" markerHeight=\"8\" orient=\"auto-start-reverse\">\n      <path d=\"M0,2 L10,5 L0,8 z\"/>\n    </marker>\n  </defs>\n");
+        sheet.svg.push_str("  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+        // top view
+        for (x, y) in self.mask.cells() {
+            let c = top(self.mask.cell_center(x, y));
+            let half = SIZE / 2.0;
+            let corners = [
+                c + dvec2(-half, -half),
+                c + dvec2(half, -half),
+                c + dvec2(half, half),
+                c + dvec2(-half, half),
+            ];
+            sheet.polygon(&corners, "grid");
+        }
+        let outline: Vec<DVec2> = self.mask.polygon(0.0).into_iter().map(top).collect();
+        sheet.polygon(&outline, "");
+        if self.open {
+            let inner: Vec<DVec2> = self
+                .mask
+                .polygon(self.walls.thickness)
+                .into_iter()
+                .map(top)
+                .collect();
+            sheet.polygon(&inner, "thin");
+        }
+        if let Some(divider) = &self.divider {
+            for (axis, pos) in &divider.slots {
+                for (lo, hi) in divider.spans(&self.mask, &self.walls, *axis, *pos) {
+                    let (a, b) = match axis {
+                        Axis::X => (dvec2(*pos, lo), dvec2(*pos, hi)),
+                        Axis::Y => (dvec2(lo, *pos), dvec2(hi, *pos)),
+                    };
+                    sheet.line(top(a), top(b), "slot");
+                }
+            }
+        }
+        // magnets are under the part so they are drawn hidden
+        for pos in &magnets {
+            sheet.circle(top(*pos), MAGNET_DIAMETER / 2.0, "thin hidden");
+        }
+        for (n, hole) in self.holes.iter().enumerate() {
+            let c = top(hole.center);
+            sheet.circle(c, hole.diameter / 2.0, "thin");
+            sheet.line(c - dvec2(1.0, 0.0), c + dvec2(1.0, 0.0), "thin");
+            sheet.line(c - dvec2(0.0, 1.0), c + dvec2(0.0, 1.0), "thin");
+            let tag = c + dvec2(1.0, -1.0) * (hole.diameter * 0.35 + 0.5);
+            sheet.text(tag, 0.0, "start", &(n + 1).to_string());
+        }
+        // overall size above and to the left
+        let left_back = top(dvec2(-width / 2.0, depth / 2.0));
+        let right_back = top(dvec2(width / 2.0, depth / 2.0));
+        let left_front = top(dvec2(-width / 2.0, -depth / 2.0));
+        sheet.dimension(right_back, left_back, 16.0, &mm(width));
+        sheet.dimension(left_back, left_front, 16.0, &mm(depth));
+        // compartments between the inner walls and the slots
+        if self.open {
+            for (lo, hi) in self.compartments(Axis::X) {
+                let a = top(dvec2(hi, depth / 2.0));
+                let b = top(dvec2(lo, depth / 2.0));
+                sheet.dimension(a, b, 7.0, &mm(hi - lo));
+            }
+            for (lo, hi) in self.compartments(Axis::Y) {
+                let a = top(dvec2(-width / 2.0, hi));
+                let b = top(dvec2(-width / 2.0, lo));
+                sheet.dimension(a, b, 7.0, &mm(hi - lo));
+            }
+        }
+
+        // front view , looking at the front face , with the slots
+        // across the width
+        self.elevation(&mut sheet, &front, width, Axis::X);
+        sheet.dimension(
+            front(-width / 2.0, 0.0),
+            front(-width / 2.0, total),
+            -10.0,
+            &mm(total),
+        );
+        if let Some(h) = self.height {
+            let wall = V_UNIT * h as f64;
+            sheet.dimension(
+                front(width / 2.0, V_UNIT * (h + 1) as f64),
+                front(width / 2.0, V_UNIT),
+                -10.0,
+                &format!("{} u = {} mm", h, mm(wall)),
+            );
+            sheet.dimension(
+                front(width / 2.0, V_UNIT),
+                front(width / 2.0, 0.0),
+                -20.0,
+                &mm(V_UNIT),
+            );
+        }
+
+        // side view , looking at the right face with the back on the right
+        self.elevation(&mut sheet, &side, depth, Axis::Y);
+        sheet.dimension(
+            side(-depth / 2.0, 0.0),
+            side(depth / 2.0, 0.0),
+            8.0,
+            &mm(depth),
+        );
+
+        // title and hole table under the views
+        let mut row = dvec2(MARGIN, 3.5 * MARGIN + depth + total - MARGIN / 2.0);
+        let mut title = self.title.clone();
+        title.push_str(&format!(
+            "  {} x {} cells , {} x {} x {} mm , scale 1:1",
+            self.mask.width(),
+            self.mask.height(),
+            mm(width),
+            mm(depth),
+            mm(total)
+        ));
+        if let Some(h) = self.height {
+            title.push_str(&format!(" , {} u", h));
+        }
+        sheet.text(row, 0.0, "start", &title);
+        if !magnets.is_empty() {
+            row.y += ROW;
+            let cell = Connector::magnets()[0];
+            sheet.text(
+                row,
+                0.0,
+                "start",
+                &format!(
+                    "{} magnet holes , {} mm at +-{} , +-{} from each cell center , from below",
+                    magnets.len(),
+                    mm(MAGNET_DIAMETER),
+                    mm(cell.x),
+                    mm(cell.y)
+                ),
+            );
+        }
+        if !self.holes.is_empty() {
+            row.y += ROW;
+            sheet.text(row, 0.0, "start", "hole   kind   x   y   diameter");
+            for (n, hole) in self.holes.iter().enumerate() {
+                row.y += ROW;
+                sheet.text(
+                    row,
+                    0.0,
+                    "start",
+                    &format!(
+                        "{}   {}   {}   {}   {}",
+                        n + 1,
+                        hole.kind,
+                        mm(hole.center.x),
+                        mm(hole.center.y),
+                        mm(hole.diameter)
+                    ),
+                );
+            }
+        }
+        sheet.svg.push_str("</svg>\n");
+        sheet.svg
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.svg()).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gridfin::WALL_THICKNESS as WALL;
+
+    fn close(a: &[(f64, f64)], b: &[(f64, f64)]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(p, q)| (p.0 - q.0).abs() < 1e-9 && (p.1 - q.1).abs() < 1e-9)
+    }
+
+    #[test]
+    fn compartments_run_between_the_walls_and_slots() {
+        let mut drawing = Drawing::new(&Mask::rect(2, 1), Some(3));
+        let inner = SIZE - WALL;
+        assert!(close(&drawing.compartments(Axis::X), &[(-inner, inner)]));
+        drawing.divider = Some(Divider::new(vec![(Axis::X, 5.0)]));
+        let x = drawing.compartments(Axis::X);
+        assert!(close(&x, &[(-inner, 5.0), (5.0, inner)]));
+        let y = drawing.compartments(Axis::Y);
+        assert!(close(&y, &[(WALL - SIZE / 2.0, SIZE / 2.0 - WALL)]));
+    }
+
+    #[test]
+    fn masked_compartments_follow_the_cells() {
+        // a U , the back row is two arms
+        let mask = Mask::from_ascii("#.#\n###\n").unwrap();
+        let mut drawing = Drawing::new(&mask, Some(3));
+        let arm = 1.5 * SIZE - WALL;
+        let arms = [(-arm, -SIZE / 2.0 - WALL), (SIZE / 2.0 + WALL, arm)];
+        assert!(close(&drawing.compartments(Axis::X), &arms));
+        // a thinner shell leaves more room
+        drawing.walls.thickness = 1.0;
+        let x = drawing.compartments(Axis::X);
+        assert!((x[0].0 + 1.5 * SIZE - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn filled_bins_have_no_cavity() {
+        let mut drawing = Drawing::new(&Mask::rect(1, 1), Some(3));
+        let svg = drawing.svg();
+        drawing.open = false;
+        let filled = drawing.svg();
+        // the inner outline and the two compartment dimensions go
+        assert_eq!(
+            svg.matches("<polygon").count(),
+            filled.matches("<polygon").count() + 1
+        );
+        assert!(svg.matches("class=\"dim\"").count() > filled.matches("class=\"dim\"").count());
+    }
+}
@@ -0,0 +1,212 @@
+// File export
+// single shapes go out as stl or step , a list of bodies can be
+// kept apart in a 3mf so a multi material printer can colour them
+
+use std::fs::File;
+use std::io::Write;
+
+use clap::ValueEnum;
+use opencascade::primitives::Shape;
+use serde::{Deserialize, Serialize};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Stl,
+    Step,
+    #[value(name = "3mf")]
+    #[serde(rename = "3mf")]
+    ThreeMf,
+}
+
+impl Format {
+    pub fn ext(&self) -> &'static str {
+        match self {
+            Format::Stl => "stl",
+            Format::Step => "step",
+            Format::ThreeMf => "3mf",
+        }
+    }
+}
+
+// a named part of the print , with a colour hint for the slicer
+#[derive(Clone)]
+pub struct Body {
+    pub name: String,
+    pub shape: Shape,
+    pub color: Option<[u8; 3]>,
+}
+
+impl Body {
+    pub fn new(name: &str, shape: Shape) -> Self {
+        Self {
+            name: name.to_owned(),
+            shape,
+            color: None,
+        }
+    }
+}
+
+// "rrggbb" or "#rrggbb"
+pub fn parse_color(text: &str) -> Result<[u8; 3], String> {
+    let hex = text.trim_start_matches('#');
+    if hex.len() != 6 {
+        return Err(format!("bad colour {:?} , expected rrggbb", text));
+    }
+    // from_str_radix would take a sign , and slicing needs ascii
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("bad colour {:?}", text));
+    }
+    let byte = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("bad colour {:?}", text))
+    };
+    Ok([byte(0)?, byte(2)?, byte(4)?])
+}
+
+// stl and step hold one shape , so the bodies are fused
+pub fn write(path: &str, format: Format, bodies: Vec<Body>) -> Result<(), String> {
+    if format == Format::ThreeMf {
+        return write_3mf(path, &bodies);
+    }
+    let mut bodies = bodies.into_iter();
+    let mut shape = bodies.next().ok_or("nothing to write".to_owned())?.shape;
+    for body in bodies {
+        shape = shape.union(&body.shape).into();
+    }
+    // this needs STL resolution
+    match format {
+        Format::Step => shape.write_step(path),
+        _ => shape.write_stl_with_tolerance(path, 0.1),
+    }
+    .map_err(|e| format!("{}: {:?}", path, e))
+}
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+
+const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>
+"#;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// the model xml , one object per body , all in one build item
+// so the slicer keeps them lined up as a single part
+fn model(bodies: &[Body]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<model unit=\"millimeter\" xml:lang=\"en-US\" xmlns=\"http://schemas.microsoft.com/3dmanufacturing/core/2015/02\">\n");
+    xml.push_str(" <resources>\n");
+    // one base material per body , id 1
+    xml.push_str("  <basematerials id=\"1\">\n");
+    for body in bodies {
+        let [r, g, b] = body.color.unwrap_or([0x80, 0x80, 0x80]);
+        xml.push_str(&format!(
+            "   <base name=\"{}\" displaycolor=\"#{:02X}{:02X}{:02X}\"/>\n",
+            escape(&body.name),
+            r,
+            g,
+            b
+        ));
+    }
+    xml.push_str("  </basematerials>\n");
+    for (i, body) in bodies.iter().enumerate() {
+        let mesh = body.shape.mesh();
+        xml.push_str(&format!(
+            "  <object id=\"{}\" name=\"{}\" type=\"model\" pid=\"1\" pindex=\"{}\">\n   <mesh>\n    <vertices>\n",
+            i + 2,
+            escape(&body.name),
+            i
+        ));
+        for v in &mesh.vertices {
+            xml.push_str(&format!(
+                "     <vertex x=\"{:.4}\" y=\"{:.4}\" z=\"{:.4}\"/>\n",
+                v.x, v.y, v.z
+            ));
+        }
+        xml.push_str("    </vertices>\n    <triangles>\n");
+        for t in mesh.indices.chunks(3) {
+            xml.push_str(&format!(
+                "     <triangle v1=\"{}\" v2=\"{}\" v3=\"{}\"/>\n",
+                t[0], t[1], t[2]
+            ));
+        }
+        xml.push_str("    </triangles>\n   </mesh>\n  </object>\n");
+    }
+    // a parent object holding all the bodies
+    let parent = bodies.len() + 2;
+    xml.push_str(&format!(
+        "  <object id=\"{}\" name=\"{}\" type=\"model\">\n   <components>\n",
+        parent,
+        escape(&bodies[0].name)
+    ));
+    for i in 0..bodies.len() {
+        xml.push_str(&format!("    <component objectid=\"{}\"/>\n", i + 2));
+    }
+    xml.push_str("   </components>\n  </object>\n");
+    xml.push_str(" </resources>\n");
+    xml.push_str(&format!(
+        " <build>\n  <item objectid=\"{}\"/>\n </build>\n</model>\n",
+        parent
+    ));
+    xml
+}
+
+pub fn write_3mf(path: &str, bodies: &[Body]) -> Result<(), String> {
+    if bodies.is_empty() {
+        return Err("nothing to write".to_owned());
+    }
+    let err = |e: &dyn std::fmt::Display| format!("{}: {}", path, e);
+    let file = File::create(path).map_err(|e| err(&e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES.to_owned()),
+        ("_rels/.rels", RELS.to_owned()),
+        ("3D/3dmodel.model", model(bodies)),
+    ];
+    for (name, text) in parts {
+        zip.start_file(name, options).map_err(|e| err(&e))?;
+        zip.write_all(text.as_bytes()).map_err(|e| err(&e))?;
+    }
+    zip.finish().map_err(|e| err(&e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colours() {
+        assert_eq!(parse_color("#ff8000"), Ok([255, 128, 0]));
+        assert_eq!(parse_color("00A0fF"), Ok([0, 160, 255]));
+    }
+
+    #[test]
+    fn bad_colours() {
+        for text in ["", "#fff", "#ff80000", "gg0000", "+f+f+f", "aébcd"] {
+            assert!(parse_color(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(Format::Stl.ext(), "stl");
+        assert_eq!(Format::Step.ext(), "step");
+        assert_eq!(Format::ThreeMf.ext(), "3mf");
+    }
+}
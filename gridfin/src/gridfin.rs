@@ -1,60 +1,230 @@
 // ref https://gridfinity.xyz/specification/
 
-use glam::{dvec3, DVec3};
+use glam::{dvec2, dvec3, DVec2, DVec3};
 use opencascade::{
     primitives::{Direction, Shape, Solid},
     workplane::Workplane,
 };
 
-const SIZE: f64 = 41.5;
-const FILLET: f64 = 3.75;
-const INNER_FILLET: f64 = 3.20;
-const MID_LIFT: f64 = 4.75;
-const V_UNIT: f64 = 7.0;
-const WALL_THICKNESS: f64 = 2.15;
+use crate::base::BaseFloor;
+use crate::cache;
+use crate::divider::Divider;
+use crate::mask::Mask;
+use crate::pattern::Pattern;
+use crate::tier::Tiers;
+
+pub(crate) const SIZE: f64 = 41.5;
+pub(crate) const FILLET: f64 = 3.75;
+pub(crate) const MID_LIFT: f64 = 4.75;
+pub(crate) const V_UNIT: f64 = 7.0;
+pub(crate) const WALL_THICKNESS: f64 = 2.15;
+pub(crate) const CAVITY_FILLET: f64 = 0.8;
+pub(crate) const MAGNET_DIAMETER: f64 = 6.5;
+
+// the wall and floor sizes of an open bin , the spec values by default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Walls {
+    // side wall thickness
+    pub thickness: f64,
+    // extra floor , the cavity starts this far above the base
+    pub floor: f64,
+    // outside corner radius
+    pub fillet: f64,
+    // radius along the bottom of the cavity
+    pub cavity_fillet: f64,
+}
+
+impl Default for Walls {
+    fn default() -> Self {
+        Self {
+            thickness: WALL_THICKNESS,
+            floor: 0.0,
+            fillet: FILLET,
+            cavity_fillet: CAVITY_FILLET,
+        }
+    }
+}
+
+impl Walls {
+    const MIN_THICKNESS: f64 = 0.8;
+    const MAX_THICKNESS: f64 = 6.0;
+    const MIN_FILLET: f64 = 0.5;
+    // keep this much cavity above the floor
+    const MIN_DEPTH: f64 = 2.0;
+
+    // the cavity corners stay parallel to the outside , but never
+    // tighter than the fillet along the bottom , and never square as
+    // the corner arcs can't have a zero radius
+    pub fn inner_fillet(&self) -> f64 {
+        (self.fillet - self.thickness)
+            .max(self.cavity_fillet)
+            .max(Walls::MIN_FILLET)
+    }
+
+    // the bottom of the cavity
+    pub fn bottom(&self) -> f64 {
+        V_UNIT + self.floor
+    }
+
+    // how deep the cavity is in a bin of `height` units
+    pub fn depth(&self, height: usize) -> f64 {
+        V_UNIT * height as f64 - self.floor
+    }
+
+    pub fn check(&self, height: usize) -> Result<(), String> {
+        if self.thickness < Walls::MIN_THICKNESS || self.thickness > Walls::MAX_THICKNESS {
+            return Err(format!(
+                "wall thickness {} is not between {} and {}mm",
+                self.thickness,
+                Walls::MIN_THICKNESS,
+                Walls::MAX_THICKNESS
+            ));
+        }
+        // a larger radius would cut into the tops of the feet
+        if self.fillet < Walls::MIN_FILLET || self.fillet > FILLET {
+            return Err(format!(
+                "corner radius {} is not between {} and {}mm",
+                self.fillet,
+                Walls::MIN_FILLET,
+                FILLET
+            ));
+        }
+        if self.floor < 0.0 || self.depth(height) < Walls::MIN_DEPTH {
+            return Err(format!(
+                "floor thickness {} does not fit in a bin {} units high",
+                self.floor, height
+            ));
+        }
+        if self.cavity_fillet < 0.0 || 2.0 * self.cavity_fillet >= self.depth(height) {
+            return Err(format!(
+                "cavity fillet {} does not fit in a {}mm deep cavity",
+                self.cavity_fillet,
+                self.depth(height)
+            ));
+        }
+        Ok(())
+    }
+}
+
+// the top of the walls of a bin of `height` units , where the feet
+// of the next bin come to rest
+pub fn stack_height(height: usize) -> f64 {
+    V_UNIT * (height + 1) as f64
+}
+
+// the overall height of a bin , lip included
+pub fn total_height(height: usize) -> f64 {
+    stack_height(height) + MID_LIFT
+}
+
+// the most units that fit in `mm` , measured to the top of the lip
+// or to the stacking line , so a stack still ends on a whole unit
+pub fn units_for(mm: f64, lip: bool) -> Result<usize, String> {
+    let lip_height = if lip { MID_LIFT } else { 0.0 };
+    // a little slack so exact heights are not lost to rounding
+    let units = ((mm - lip_height) / V_UNIT + 1e-9).floor() - 1.0;
+    if units < 1.0 {
+        return Err(format!(
+            "{}mm is less than the {}mm of a one unit bin",
+            mm,
+            if lip { total_height(1) } else { stack_height(1) }
+        ));
+    }
+    Ok(units as usize)
+}
+
+// the optional extras for an open bin
+#[derive(Debug, Clone, Default)]
+pub struct BinOptions {
+    // wall , floor and corner sizes
+    pub walls: Walls,
+    // slots in the walls for divider plates
+    pub divider: Option<Divider>,
+    // holes through the walls
+    pub pattern: Option<Pattern>,
+    // stepped or sloped floor
+    pub tiers: Option<Tiers>,
+}
 
 // this is the wall construction
 // currently it just a filleted empty subtraction
 // it should parse a shape in to subtract out so
 // a series of different interiors can be buit.
 pub struct Wall {
-    x: usize,
-    y: usize,
+    mask: Mask,
     height: usize,
     filled: bool,
+    cutters: Vec<Shape>,
+    options: BinOptions,
 }
 
 impl Wall {
-    fn new(x: usize, y: usize, height: usize, filled: bool) -> Self {
+    fn new(mask: &Mask, height: usize, filled: bool) -> Self {
         Self {
-            x,
-            y,
+            mask: mask.clone(),
             height,
             filled,
+            cutters: Vec::new(),
+            options: BinOptions::default(),
         }
     }
 
+    // subtract an interior shape from the wall
+    fn cut(&mut self, cutter: Shape) {
+        self.cutters.push(cutter);
+    }
+
     fn shape(&mut self) -> Shape {
-        let width: f64 = SIZE * self.x as f64;
-        let depth: f64 = SIZE * self.y as f64;
-        let mut wall_outline = Workplane::xy().rect(width, depth);
-        wall_outline = wall_outline.fillet(FILLET).translate(dvec3(0.0, 0.0, V_UNIT));
+        let key = (&self.mask, self.height, self.filled, &self.options);
+        let mut wall = cache::shape("wall", &key, || self.body());
+        for cutter in &self.cutters {
+            wall = wall.subtract(cutter).into();
+        }
+        wall
+    }
+
+    // the wall with the extras from the options , no cutters
+    fn body(&self) -> Shape {
+        let walls = &self.options.walls;
+        let wall_outline = self
+            .mask
+            .outline(0.0, walls.fillet)
+            .translate(dvec3(0.0, 0.0, V_UNIT));
         let mut wall: Shape = wall_outline
             .to_face()
             .extrude(dvec3(0.0, 0.0, V_UNIT * self.height as f64))
             .into();
         if !self.filled {
-            let mut cutout_outline =
-                Workplane::xy().rect(width - 2.0 * WALL_THICKNESS, depth - 2.0 * WALL_THICKNESS);
-            cutout_outline = cutout_outline.fillet(INNER_FILLET).translate(dvec3(0.0, 0.0, V_UNIT));
+            let cutout_outline = self
+                .mask
+                .outline(walls.thickness, walls.inner_fillet())
+                .translate(dvec3(0.0, 0.0, walls.bottom()));
             let mut cutout: Shape = cutout_outline
                 .to_face()
-                .extrude(dvec3(0.0, 0.0, V_UNIT * self.height as f64))
+                .extrude(dvec3(0.0, 0.0, walls.depth(self.height)))
                 .into();
 
-            let bot_edges = cutout.faces().farthest(Direction::NegZ).edges();
-            cutout  = cutout.fillet_edges(0.8, bot_edges);
+            if walls.cavity_fillet > 0.0 {
+                let bot_edges = cutout.faces().farthest(Direction::NegZ).edges();
+                cutout = cutout.fillet_edges(walls.cavity_fillet, bot_edges);
+            }
+            // steps put back into the bottom of the cavity
+            if let Some(tiers) = &self.options.tiers {
+                cutout = cutout.subtract(&tiers.shape(&self.mask, walls)).into();
+            }
             wall = wall.subtract(&cutout).into();
+            // slots for the removable dividers
+            if let Some(divider) = &self.options.divider {
+                if let Some(slots) = divider.slots(&self.mask, walls, self.height) {
+                    wall = wall.subtract(&slots).into();
+                }
+            }
+            // see through walls
+            if let Some(pattern) = &self.options.pattern {
+                if let Some(holes) = pattern.cutter(&self.mask, walls, self.height) {
+                    wall = wall.subtract(&holes).into();
+                }
+            }
         }
         wall
     }
@@ -62,33 +232,50 @@ impl Wall {
 
 #[derive(Debug)]
 pub struct Plate {
-    x: usize,
-    y: usize,
+    mask: Mask,
+    magnets: bool,
+    // outside corner radius above the feet
+    fillet: f64,
 }
 
 impl Plate {
     pub fn new(x: usize, y: usize) -> Self {
-        Self { x: x, y: y }
+        Self::from_mask(&Mask::rect(x, y))
+    }
+
+    pub fn from_mask(mask: &Mask) -> Self {
+        Self {
+            mask: mask.clone(),
+            magnets: true,
+            fillet: FILLET,
+        }
     }
 
     pub fn shape(&mut self) -> Shape {
-        let mut plate_outline = Workplane::xy().rect(SIZE * self.x as f64, SIZE * self.y as f64);
-        plate_outline = plate_outline.fillet(FILLET);
-        plate_outline = plate_outline.translate(dvec3(0.0, 0.0, MID_LIFT));
+        let key = (&self.mask, self.magnets, self.fillet);
+        cache::shape("plate", &key, || self.body())
+    }
+
+    fn body(&self) -> Shape {
+        let plate_outline = self
+            .mask
+            .outline(0.0, self.fillet)
+            .translate(dvec3(0.0, 0.0, MID_LIFT));
         let mut plate: Shape = plate_outline
             .to_face()
             .extrude(dvec3(0.0, 0.0, V_UNIT - MID_LIFT))
             .into();
-        for x in 0..self.x {
-            for y in 0..self.y {
-                println!("{:?},{:?}", x, y);
-                let mut base = Connector::connector();
-                // origin is the center of the plate
-                let x_pos = (SIZE * x as f64) - (SIZE * (self.x - 1) as f64) / 2.0;
-                let y_pos = (SIZE * y as f64) - (SIZE * (self.y - 1) as f64) / 2.0;
-                base.set_global_translation(dvec3(x_pos, y_pos, 0.0));
-                plate = plate.union(&base).into();
-            }
+        for (x, y) in self.mask.cells() {
+            println!("{:?},{:?}", x, y);
+            let mut base = if self.magnets {
+                Connector::connector()
+            } else {
+                Connector::new(1, 1, Connector::PLAIN).shape()
+            };
+            // origin is the center of the plate
+            let pos = self.mask.cell_center(x, y);
+            base.set_global_translation(dvec3(pos.x, pos.y, 0.0));
+            plate = plate.union(&base).into();
         }
         plate
     }
@@ -96,6 +283,7 @@ impl Plate {
 
 // as there are three versions of the bottom of the gridfinity system
 // base plate , block bottom and top lip a config should be used to seperate them
+#[derive(Debug)]
 pub struct BaseConfig {
     lower_size: f64,
     lower_fillet: f64,
@@ -105,8 +293,7 @@ pub struct BaseConfig {
 }
 
 pub struct Connector {
-    x: usize,
-    y: usize,
+    mask: Mask,
     config: BaseConfig,
 }
 
@@ -119,6 +306,15 @@ impl Connector {
         chamfer: true,
     };
 
+    // the under side without the magnet holes
+    pub const PLAIN: BaseConfig = BaseConfig {
+        lower_size: 37.2,
+        lower_fillet: 1.6,
+        mid_fillet: 2.6,
+        magnets: false,
+        chamfer: true,
+    };
+
     pub const LIP: BaseConfig = BaseConfig {
         lower_size: 37.2,
         lower_fillet: 1.6,
@@ -127,7 +323,7 @@ impl Connector {
         chamfer: false,
     };
 
-    const LOWER_SIZE: f64 = 37.2;
+    pub(crate) const LOWER_SIZE: f64 = 37.2;
     const LOWER_FILLET: f64 = 1.6;
     const LOWER_HEIGHT: f64 = 2.6;
 
@@ -137,17 +333,24 @@ impl Connector {
     const MAG_INSET: f64 = 5.6;
 
     pub fn new(x: usize, y: usize, config: BaseConfig) -> Self {
-        Self { x, y, config }
+        Self::from_mask(&Mask::rect(x, y), config)
+    }
+
+    pub fn from_mask(mask: &Mask, config: BaseConfig) -> Self {
+        Self {
+            mask: mask.clone(),
+            config,
+        }
     }
 
     pub fn shape(&mut self) -> Shape {
+        cache::shape("connector", &(&self.mask, &self.config), || self.body())
+    }
+
+    fn body(&self) -> Shape {
         // lower section
-        let inset: f64 = SIZE - self.config.lower_size;
-        let mut outline = Workplane::xy().rect(
-            (SIZE * self.x as f64) - inset,
-            (SIZE * self.y as f64) - inset,
-        );
-        outline = outline.fillet(Connector::MID_FILLET);
+        let inset: f64 = (SIZE - self.config.lower_size) / 2.0;
+        let outline = self.mask.outline(inset, Connector::MID_FILLET);
         let mut lower: Shape = outline
             .to_face()
             .extrude(dvec3(0.0, 0.0, Connector::LOWER_HEIGHT))
@@ -159,40 +362,51 @@ impl Connector {
         }
         // cut the magnets out
         if self.config.magnets {
-            let mag_pos = Connector::LOWER_SIZE / 2.0 - Connector::MAG_INSET;
-            let mags = [
-                Magnet::new(dvec3(mag_pos, mag_pos, 0.0)),
-                Magnet::new(dvec3(-mag_pos, mag_pos, 0.0)),
-                Magnet::new(dvec3(mag_pos, -mag_pos, 0.0)),
-                Magnet::new(dvec3(-mag_pos, -mag_pos, 0.0)),
-            ];
-            for mut m in mags {
+            for pos in Connector::magnets() {
+                let mut m = Magnet::new(dvec3(pos.x, pos.y, 0.0));
                 // TODO will change to new bool
                 lower = lower.subtract(&m.shape()).into();
             }
         }
         // middle
-        let mut mid_lower =
-            Workplane::xy().rect(SIZE * self.x as f64 - inset, SIZE * self.y as f64 - inset);
-        mid_lower = mid_lower.fillet(Connector::MID_FILLET).translate(dvec3(0.0, 0.0, Connector::LOWER_HEIGHT));
-        let mut mid_upper = Workplane::xy().rect(SIZE * self.x as f64, SIZE * self.y as f64);
-        mid_upper = mid_upper.fillet(FILLET).translate(dvec3(0.0, 0.0, MID_LIFT));
+        let mid_lower = self
+            .mask
+            .outline(inset, Connector::MID_FILLET)
+            .translate(dvec3(0.0, 0.0, Connector::LOWER_HEIGHT));
+        let mid_upper = self.mask.outline(0.0, FILLET).translate(dvec3(0.0, 0.0, MID_LIFT));
         let mid = Solid::loft([&mid_lower, &mid_upper]).into();
 
         lower = lower.union(&mid).into();
         lower
     }
 
+    // magnet hole centers from the center of a cell
+    pub fn magnets() -> [DVec2; 4] {
+        let mag_pos = Connector::LOWER_SIZE / 2.0 - Connector::MAG_INSET;
+        [
+            dvec2(mag_pos, mag_pos),
+            dvec2(-mag_pos, mag_pos),
+            dvec2(mag_pos, -mag_pos),
+            dvec2(-mag_pos, -mag_pos),
+        ]
+    }
+
     pub fn connector() -> Shape {
         // just git back the under plate
         let mut s = Connector::new(1, 1, Self::UNDER);
         s.shape()
     }
 
-    pub fn lip(x: usize, y: usize, height: usize) -> Shape {
-        let mut s = Connector::new(x, y, Self::LIP).shape();
-        let mut plate_outline = Workplane::xy().rect(SIZE * x as f64, SIZE * y as f64);
-        plate_outline = plate_outline.fillet(FILLET);
+    // the stacking lip on top of a bin , `fillet` is the outside corner radius
+    pub fn lip(mask: &Mask, height: usize, fillet: f64) -> Shape {
+        cache::shape("lip", &(mask, height, fillet), || {
+            Connector::make_lip(mask, height, fillet)
+        })
+    }
+
+    fn make_lip(mask: &Mask, height: usize, fillet: f64) -> Shape {
+        let mut s = Connector::from_mask(mask, Self::LIP).shape();
+        let plate_outline = mask.outline(0.0, fillet);
         let mut plate: Shape = plate_outline
             .to_face()
             .extrude(dvec3(0.0, 0.0, MID_LIFT))
@@ -213,7 +427,7 @@ pub struct Magnet {
 impl Magnet {
     fn new(pos: DVec3) -> Self {
         Self {
-            diameter: 6.5,
+            diameter: MAGNET_DIAMETER,
             thickness: 2.0,
             pos: pos,
         }
@@ -227,12 +441,69 @@ impl Magnet {
     }
 }
 
-pub fn full(x: usize, y: usize, height: usize) -> Shape {
-    let mut pl = Plate::new(x, y).shape();
+pub fn full(mask: &Mask, height: usize) -> Shape {
+    open(mask, height, &BinOptions::default())
+}
+
+// a solid bin with the interior shapes cut out of it
+pub fn filled(mask: &Mask, height: usize, cutters: Vec<Shape>) -> Shape {
+    let mut wall = Wall::new(mask, height, true);
+    for cutter in cutters {
+        wall.cut(cutter);
+    }
+    build(mask, height, wall)
+}
+
+// an open bin with the extras from the options
+pub fn open(mask: &Mask, height: usize, options: &BinOptions) -> Shape {
+    let mut wall = Wall::new(mask, height, false);
+    wall.options = options.clone();
+    build(mask, height, wall)
+}
+
+// a thin shelled bin , hollow feet and the same wall thickness
+// all the way round , for vase mode or fast prints
+pub fn lite(mask: &Mask, height: usize, thickness: f64) -> Shape {
+    let mut plate = Plate::from_mask(mask);
+    plate.magnets = false;
+    let mut body = plate.shape();
+    if height > 0 {
+        let mut wall = Wall::new(mask, height, true);
+        body = body.union(&wall.shape()).into();
+    }
+    // shell inwards leaving the top open
+    let top = body.faces().farthest(Direction::PosZ);
+    body = body.hollow(-thickness, [&top]);
+    if height > 0 {
+        let lip = Connector::lip(mask, height, FILLET);
+        body = body.union(&lip).into();
+    }
+    body
+}
+
+// a lid that sits in the lip of the bin below , with base plate
+// pockets on top so more bins stack on it
+pub fn lid(mask: &Mask) -> Shape {
+    let mut plate = Plate::from_mask(mask);
+    plate.magnets = false;
+    let mut top = BasePlate::from_mask(mask).shape();
+    top.set_global_translation(dvec3(0.0, 0.0, V_UNIT));
+    plate.shape().union(&top).into()
+}
+
+fn build(mask: &Mask, height: usize, mut wall: Wall) -> Shape {
+    let walls = wall.options.walls;
+    let mut plate = Plate::from_mask(mask);
+    plate.fillet = walls.fillet;
+    let mut pl = plate.shape();
     if height > 0 {
-        let mut wall = Wall::new(x, y, height, false);
         pl = pl.union(&wall.shape()).into();
-        let lip = Connector::lip(x, y, height);
+        let mut lip = Connector::lip(mask, height, walls.fillet);
+        // the plates slide in from the top so the slots run through the lip
+        let divider = wall.options.divider.as_ref();
+        if let Some(slots) = divider.and_then(|d| d.slots(mask, &walls, height)) {
+            lip = lip.subtract(&slots).into();
+        }
         pl = pl.union(&lip).into();
     }
     pl
@@ -241,32 +512,52 @@ pub fn full(x: usize, y: usize, height: usize) -> Shape {
 // The base plate for the bottom to mount the gf modules in
 #[derive(Debug)]
 pub struct BasePlate {
-    x: usize,
-    y: usize,
+    mask: Mask,
+    // what goes under the sockets
+    pub floor: BaseFloor,
 }
 
 impl BasePlate {
     pub fn new(x: usize, y: usize) -> Self {
-        Self { x: x, y: y }
+        Self::from_mask(&Mask::rect(x, y))
+    }
+
+    pub fn from_mask(mask: &Mask) -> Self {
+        Self {
+            mask: mask.clone(),
+            floor: BaseFloor::open(),
+        }
     }
 
     pub fn shape(&mut self) -> Shape {
-        let mut plate_outline = Workplane::xy().rect(SIZE * self.x as f64, SIZE * self.y as f64);
-        plate_outline = plate_outline.fillet(FILLET);
+        let key = (&self.mask, &self.floor);
+        cache::shape("baseplate", &key, || self.body())
+    }
+
+    // the sockets , lifted onto the floor if there is one , and
+    // anything for hanging it on the back
+    fn body(&self) -> Shape {
+        let mut plate = self.frame();
+        if let Some(floor) = self.floor.shape(&self.mask, FILLET) {
+            plate.set_global_translation(dvec3(0.0, 0.0, self.floor.height()));
+            plate = plate.union(&floor).into();
+        }
+        self.floor.hang(&self.mask, plate)
+    }
+
+    fn frame(&self) -> Shape {
+        let plate_outline = self.mask.outline(0.0, FILLET);
         let mut plate: Shape = plate_outline
             .to_face()
             .extrude(dvec3(0.0, 0.0, MID_LIFT))
             .into();
-        for x in 0..self.x {
-            for y in 0..self.y {
-                println!("generate connector ({:?},{:?})", x, y);
-                let mut base = Connector::new(1, 1, Connector::LIP).shape();
-                // origin is the center of the plate
-                let x_pos = (SIZE * x as f64) - (SIZE * (self.x - 1) as f64) / 2.0;
-                let y_pos = (SIZE * y as f64) - (SIZE * (self.y - 1) as f64) / 2.0;
-                base.set_global_translation(dvec3(x_pos, y_pos, 0.0));
-                plate = plate.subtract(&base).into();
-            }
+        for (x, y) in self.mask.cells() {
+            println!("generate connector ({:?},{:?})", x, y);
+            let mut base = Connector::new(1, 1, Connector::LIP).shape();
+            // origin is the center of the plate
+            let pos = self.mask.cell_center(x, y);
+            base.set_global_translation(dvec3(pos.x, pos.y, 0.0));
+            plate = plate.subtract(&base).into();
         }
         plate
     }
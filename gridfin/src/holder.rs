@@ -0,0 +1,260 @@
+// Holder arrays
+// fill a solid bin with rows of round or hex pockets
+// for bits , batteries , vials and the like
+
+use clap::ValueEnum;
+use glam::{dvec2, dvec3, DVec2};
+use opencascade::{primitives::Shape, workplane::Workplane};
+
+use crate::gridfin::{SIZE, V_UNIT, WALL_THICKNESS};
+use crate::mask::Mask;
+use crate::outline::Outline;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Pocket {
+    Round,
+    // sized across the flats , for hex bits
+    Hex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Layout {
+    // square rows and columns
+    Rect,
+    // every other row shifted over by half a pitch
+    Hex,
+}
+
+#[derive(Debug)]
+pub struct Holder {
+    pub pocket: Pocket,
+    pub layout: Layout,
+    pub diameter: f64,
+    pub depth: f64,
+    // wall between neighbouring pockets
+    pub spacing: f64,
+    // columns and rows , fit as many as possible if none
+    pub count: Option<(usize, usize)>,
+}
+
+impl Holder {
+    pub fn new(pocket: Pocket, diameter: f64, depth: f64) -> Self {
+        Self {
+            pocket,
+            layout: Layout::Rect,
+            diameter,
+            depth,
+            spacing: 1.5,
+            count: None,
+        }
+    }
+
+    // center to the furthest point of a pocket , hex corners are
+    // further out than the flats
+    fn radius(&self) -> f64 {
+        match self.pocket {
+            Pocket::Round => self.diameter / 2.0,
+            Pocket::Hex => self.diameter / 3f64.sqrt(),
+        }
+    }
+
+    // distance between pocket centers along a row and between rows ,
+    // hex pockets have their flats along the row so staggered rows
+    // nest flat to flat , straight rows meet corner to corner
+    fn pitch(&self) -> DVec2 {
+        let pitch = self.diameter + self.spacing;
+        match self.layout {
+            Layout::Rect => dvec2(pitch, 2.0 * self.radius() + self.spacing),
+            Layout::Hex => dvec2(pitch, pitch * 3f64.sqrt() / 2.0),
+        }
+    }
+
+    // pocket centers on a grid of cols x rows , centered on the origin
+    // staggered rows hold one less so the block stays centered
+    fn grid(&self, cols: usize, rows: usize) -> Vec<DVec2> {
+        let pitch = self.pitch();
+        let mut centers = Vec::new();
+        for row in 0..rows {
+            let count = match self.layout {
+                Layout::Hex if row % 2 == 1 => cols.saturating_sub(1),
+                _ => cols,
+            };
+            for col in 0..count {
+                let x = pitch.x * col as f64 - pitch.x * (count as f64 - 1.0) / 2.0;
+                let y = pitch.y * row as f64 - pitch.y * (rows - 1) as f64 / 2.0;
+                centers.push(dvec2(x, y));
+            }
+        }
+        centers
+    }
+
+    // does a pocket fit inside the walls of the mask
+    fn fits(&self, mask: &Mask, center: DVec2) -> bool {
+        let reach = self.radius() + WALL_THICKNESS + self.spacing;
+        let half = dvec2(
+            mask.width() as f64 * SIZE / 2.0,
+            mask.height() as f64 * SIZE / 2.0,
+        );
+        if center.x.abs() + reach > half.x || center.y.abs() + reach > half.y {
+            return false;
+        }
+        // the ring around the pocket has to sit over occupied cells
+        (0..8).all(|i| {
+            let a = std::f64::consts::TAU * i as f64 / 8.0;
+            mask.contains(center + reach * dvec2(a.cos(), a.sin()))
+        }) && mask.contains(center)
+    }
+
+    // all the pocket centers for the mask
+    pub fn centers(&self, mask: &Mask) -> Vec<DVec2> {
+        if let Some((cols, rows)) = self.count {
+            return self.grid(cols, rows);
+        }
+        // start with the bounding box and drop the ones that don't fit
+        let pitch = self.pitch();
+        let width = mask.width() as f64 * SIZE - 2.0 * WALL_THICKNESS;
+        let depth = mask.height() as f64 * SIZE - 2.0 * WALL_THICKNESS;
+        let cols = ((width - self.diameter - self.spacing) / pitch.x).floor() as usize + 1;
+        let rows = ((depth - self.diameter - self.spacing) / pitch.y).floor() as usize + 1;
+        self.grid(cols, rows)
+            .into_iter()
+            .filter(|c| self.fits(mask, *c))
+            .collect()
+    }
+
+    fn pocket(&self, center: DVec2, bottom: f64) -> Shape {
+        let rim = match self.pocket {
+            Pocket::Round => Workplane::xy().circle(center.x, center.y, self.diameter / 2.0),
+            Pocket::Hex => {
+                // corners up and down , flats facing along the row
+                let radius = self.radius();
+                let points = (0..6)
+                    .map(|i| {
+                        let a = std::f64::consts::TAU * (i as f64 + 0.5) / 6.0;
+                        center + radius * dvec2(a.cos(), a.sin())
+                    })
+                    .collect();
+                Outline::new(points).unwrap().wire()
+            }
+        };
+        let rim = rim.translate(dvec3(0.0, 0.0, bottom));
+        rim.to_face().extrude(dvec3(0.0, 0.0, self.depth + 1.0)).into()
+    }
+
+    pub fn check(&self, mask: &Mask, height: usize) -> Result<(), String> {
+        if self.depth <= 0.0 || self.depth > V_UNIT * height as f64 {
+            return Err(format!(
+                "pocket depth {} does not fit in a bin {} units high",
+                self.depth, height
+            ));
+        }
+        let centers = self.centers(mask);
+        if centers.is_empty() {
+            return Err("no pockets fit in the bin".to_owned());
+        }
+        // a count is taken as asked , so every pocket has to fit
+        if let Some((cols, rows)) = self.count {
+            if !centers.iter().all(|c| self.fits(mask, *c)) {
+                return Err(format!("{} x {} pockets don't fit in the bin", cols, rows));
+            }
+        }
+        Ok(())
+    }
+
+    // the cutter for a bin of `height` units
+    pub fn shape(&mut self, mask: &Mask, height: usize) -> Result<Shape, String> {
+        let top = V_UNIT * (height + 1) as f64;
+        self.check(mask, height)?;
+        let centers = self.centers(mask);
+        let mut cutter = self.pocket(centers[0], top - self.depth);
+        for center in &centers[1..] {
+            cutter = cutter.union(&self.pocket(*center, top - self.depth)).into();
+        }
+        Ok(cutter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(diameter: f64) -> Holder {
+        Holder::new(Pocket::Round, diameter, 10.0)
+    }
+
+    #[test]
+    fn hex_pockets_reach_their_corners() {
+        assert_eq!(round(10.0).radius(), 5.0);
+        let hex = Holder::new(Pocket::Hex, 6.35, 10.0);
+        assert!((hex.radius() - 6.35 / 3f64.sqrt()).abs() < 1e-12);
+        // straight rows are spaced for the corners
+        assert!((hex.pitch().y - (2.0 * hex.radius() + hex.spacing)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn staggered_rows_hold_one_less() {
+        let mut holder = round(10.0);
+        holder.layout = Layout::Hex;
+        let centers = holder.grid(4, 3);
+        assert_eq!(centers.len(), 4 + 3 + 4);
+        // the short row sits in the gaps of the long ones
+        let pitch = holder.pitch();
+        assert!((centers[4].x - (centers[0].x + pitch.x / 2.0)).abs() < 1e-9);
+        let sum: DVec2 = centers.iter().sum();
+        assert!(sum.length() < 1e-9);
+    }
+
+    #[test]
+    fn pockets_fill_a_cell() {
+        let mask = Mask::rect(1, 1);
+        let holder = round(10.0);
+        let centers = holder.centers(&mask);
+        assert_eq!(centers.len(), 9);
+        assert!(centers.iter().all(|c| holder.fits(&mask, *c)));
+        assert!(holder.check(&mask, 3).is_ok());
+    }
+
+    #[test]
+    fn pockets_keep_apart() {
+        let mask = Mask::rect(3, 2);
+        for layout in [Layout::Rect, Layout::Hex] {
+            let mut holder = round(8.0);
+            holder.layout = layout;
+            let centers = holder.centers(&mask);
+            for (i, a) in centers.iter().enumerate() {
+                for b in &centers[i + 1..] {
+                    assert!(a.distance(*b) >= holder.diameter + holder.spacing - 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pockets_stay_off_empty_cells() {
+        let mask = Mask::from_ascii("#.\n##\n").unwrap();
+        let holder = round(10.0);
+        let centers = holder.centers(&mask);
+        assert!(!centers.is_empty());
+        let empty = mask.cell_center(1, 1);
+        for c in &centers {
+            assert!((*c - empty).abs().max_element() > SIZE / 2.0);
+        }
+    }
+
+    #[test]
+    fn counts_and_depths_are_checked() {
+        let mask = Mask::rect(1, 1);
+        let mut holder = round(10.0);
+        holder.count = Some((3, 3));
+        assert!(holder.check(&mask, 3).is_ok());
+        holder.count = Some((4, 3));
+        assert_eq!(
+            holder.check(&mask, 3).unwrap_err(),
+            "4 x 3 pockets don't fit in the bin"
+        );
+        holder.count = None;
+        holder.depth = 30.0;
+        assert!(holder.check(&mask, 3).is_err());
+        assert!(round(50.0).check(&mask, 3).is_err());
+    }
+}
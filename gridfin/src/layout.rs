@@ -0,0 +1,381 @@
+// Drawer layout planner
+// pack a list of bin footprints onto a base plate without overlaps ,
+// an exact search first and a first fit when that takes too long
+
+use std::cmp::Reverse;
+
+use glam::{dvec3, DVec3};
+use serde::Serialize;
+
+use crate::gridfin::{full, BasePlate, SIZE};
+use crate::mask::Mask;
+use crate::step::Part;
+
+// a footprint and how many of them are wanted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Request {
+    pub w: usize,
+    pub l: usize,
+    pub height: usize,
+    pub count: usize,
+}
+
+impl Request {
+    // "WxL" , "WxLxH" with an optional ":count" , a list is comma seperated
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        text.split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(Request::parse)
+            .collect()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (size, count) = match text.split_once(':') {
+            Some((size, count)) => (
+                size,
+                count
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("bad count in {:?}", text))?,
+            ),
+            None => (text, 1),
+        };
+        let dims: Vec<usize> = size
+            .split('x')
+            .map(|d| d.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("bad bin size {:?} , expected WxL or WxLxH", text))?;
+        let (w, l, height) = match dims[..] {
+            [w, l] => (w, l, 3),
+            [w, l, h] => (w, l, h),
+            _ => return Err(format!("bad bin size {:?} , expected WxL or WxLxH", text)),
+        };
+        if w == 0 || l == 0 || count == 0 {
+            return Err(format!("bad bin {:?} , sizes and counts start at 1", text));
+        }
+        Ok(Self {
+            w,
+            l,
+            height,
+            count,
+        })
+    }
+}
+
+// a bin placed on the plate , x and y are the cell of its front left corner
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Placement {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub l: usize,
+    pub height: usize,
+    pub rotated: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Plan {
+    pub width: usize,
+    pub length: usize,
+    pub bins: Vec<Placement>,
+    // true if the exact search finished
+    pub exact: bool,
+}
+
+struct Search {
+    width: usize,
+    length: usize,
+    grid: Vec<bool>,
+    items: Vec<Request>,
+    placed: Vec<Placement>,
+    nodes: usize,
+    limit: usize,
+}
+
+impl Search {
+    // give up on the exact search after this many tries
+    const NODE_LIMIT: usize = 500_000;
+
+    fn fits(&self, x: usize, y: usize, w: usize, l: usize) -> bool {
+        if x + w > self.width || y + l > self.length {
+            return false;
+        }
+        (y..y + l).all(|j| (x..x + w).all(|i| !self.grid[j * self.width + i]))
+    }
+
+    fn mark(&mut self, p: &Placement, value: bool) {
+        for j in p.y..p.y + p.l {
+            for i in p.x..p.x + p.w {
+                self.grid[j * self.width + i] = value;
+            }
+        }
+    }
+
+    // the ways an item can go down , both turns unless it is square
+    fn options(&self, item: &Request, rotate: bool) -> Vec<(usize, usize, bool)> {
+        let mut options = vec![(item.w, item.l, false)];
+        if rotate && item.w != item.l {
+            options.push((item.l, item.w, true));
+        }
+        options
+    }
+
+    // place item `n` and the rest , `start` keeps identical items
+    // in order so the same layout is not tried twice
+    fn place(&mut self, n: usize, start: usize, rotate: bool) -> Option<bool> {
+        if n == self.items.len() {
+            return Some(true);
+        }
+        self.nodes += 1;
+        if self.nodes > self.limit {
+            return None;
+        }
+        let item = self.items[n];
+        for pos in start..self.width * self.length {
+            let (x, y) = (pos % self.width, pos / self.width);
+            for (w, l, rotated) in self.options(&item, rotate) {
+                if !self.fits(x, y, w, l) {
+                    continue;
+                }
+                let p = Placement {
+                    x,
+                    y,
+                    w,
+                    l,
+                    height: item.height,
+                    rotated,
+                };
+                self.mark(&p, true);
+                self.placed.push(p);
+                let next_start = match self.items.get(n + 1) {
+                    Some(next) if *next == item => pos,
+                    _ => 0,
+                };
+                match self.place(n + 1, next_start, rotate) {
+                    Some(true) => return Some(true),
+                    None => return None,
+                    Some(false) => {}
+                }
+                self.placed.pop();
+                self.mark(&p, false);
+            }
+        }
+        Some(false)
+    }
+
+    // first fit , row by row from the front left
+    fn first_fit(&mut self, rotate: bool) -> Result<(), String> {
+        for item in self.items.clone() {
+            let found = (0..self.width * self.length).find_map(|pos| {
+                let (x, y) = (pos % self.width, pos / self.width);
+                self.options(&item, rotate)
+                    .into_iter()
+                    .find(|(w, l, _)| self.fits(x, y, *w, *l))
+                    .map(|(w, l, rotated)| Placement {
+                        x,
+                        y,
+                        w,
+                        l,
+                        height: item.height,
+                        rotated,
+                    })
+            });
+            match found {
+                Some(p) => {
+                    self.mark(&p, true);
+                    self.placed.push(p);
+                }
+                None => {
+                    return Err(format!(
+                        "no room left for a {}x{} bin",
+                        item.w, item.l
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn plan(width: usize, length: usize, requests: &[Request], rotate: bool) -> Result<Plan, String> {
+    pack(width, length, requests, rotate, Search::NODE_LIMIT)
+}
+
+// `limit` is the number of tries before falling back to first fit
+fn pack(
+    width: usize,
+    length: usize,
+    requests: &[Request],
+    rotate: bool,
+    limit: usize,
+) -> Result<Plan, String> {
+    let mut items: Vec<Request> = requests
+        .iter()
+        .flat_map(|r| std::iter::repeat_n(Request { count: 1, ..*r }, r.count))
+        .collect();
+    let area: usize = items.iter().map(|i| i.w * i.l).sum();
+    if area > width * length {
+        return Err(format!(
+            "the bins cover {} cells but the plate only has {}",
+            area,
+            width * length
+        ));
+    }
+    // biggest first , identical items next to each other
+    items.sort_by_key(|i| Reverse((i.w * i.l, i.w, i.l, i.height)));
+    let mut search = Search {
+        width,
+        length,
+        grid: vec![false; width * length],
+        items,
+        placed: Vec::new(),
+        nodes: 0,
+        limit,
+    };
+    match search.place(0, 0, rotate) {
+        Some(true) => Ok(Plan {
+            width,
+            length,
+            bins: search.placed,
+            exact: true,
+        }),
+        Some(false) => Err("the bins can not be fitted on the plate".to_owned()),
+        None => {
+            println!("exact search gave up , using first fit");
+            search.grid = vec![false; width * length];
+            search.placed.clear();
+            search.first_fit(rotate)?;
+            Ok(Plan {
+                width,
+                length,
+                bins: search.placed,
+                exact: false,
+            })
+        }
+    }
+}
+
+impl Plan {
+    // one letter per bin , `.` for empty cells , back row first
+    pub fn ascii(&self) -> String {
+        let letters: Vec<char> = ('A'..='Z').chain('a'..='z').chain('0'..='9').collect();
+        let mut grid = vec!['.'; self.width * self.length];
+        for (n, p) in self.bins.iter().enumerate() {
+            for j in p.y..p.y + p.l {
+                for i in p.x..p.x + p.w {
+                    grid[j * self.width + i] = letters[n % letters.len()];
+                }
+            }
+        }
+        let mut text = String::new();
+        for j in (0..self.length).rev() {
+            text.extend(&grid[j * self.width..(j + 1) * self.width]);
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // center of a placed bin from the center of the plate
+    pub fn center(&self, p: &Placement) -> (f64, f64) {
+        (
+            SIZE * (p.x as f64 + p.w as f64 / 2.0 - self.width as f64 / 2.0),
+            SIZE * (p.y as f64 + p.l as f64 / 2.0 - self.length as f64 / 2.0),
+        )
+    }
+
+    // the base plate and every bin in its place , as assembly parts
+    pub fn parts(&self) -> Vec<Part> {
+        let plate = BasePlate::new(self.width, self.length).shape();
+        let mut parts = vec![Part::new("baseplate", plate, DVec3::ZERO)];
+        for (n, p) in self.bins.iter().enumerate() {
+            let bin = full(&Mask::rect(p.w, p.l), p.height);
+            let (x, y) = self.center(p);
+            let name = format!("bin_{}_{}x{}x{}", n + 1, p.w, p.l, p.height);
+            parts.push(Part::new(&name, bin, dvec3(x, y, 0.0)));
+        }
+        parts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requests(text: &str) -> Vec<Request> {
+        Request::parse_list(text).unwrap()
+    }
+
+    // every bin on the plate , none overlapping , as many as asked for
+    fn assert_packed(plan: &Plan, requests: &[Request]) {
+        let mut grid = vec![false; plan.width * plan.length];
+        for p in &plan.bins {
+            assert!(p.x + p.w <= plan.width && p.y + p.l <= plan.length);
+            for j in p.y..p.y + p.l {
+                for i in p.x..p.x + p.w {
+                    assert!(!grid[j * plan.width + i], "overlap at {},{}", i, j);
+                    grid[j * plan.width + i] = true;
+                }
+            }
+        }
+        let wanted: usize = requests.iter().map(|r| r.count).sum();
+        assert_eq!(plan.bins.len(), wanted);
+    }
+
+    #[test]
+    fn parse_sizes_and_counts() {
+        let list: Vec<_> = requests("2x3, 1x1x6:4")
+            .iter()
+            .map(|r| (r.w, r.l, r.height, r.count))
+            .collect();
+        assert_eq!(list, [(2, 3, 3, 1), (1, 1, 6, 4)]);
+        for bad in ["2", "2x", "2x3x4x5", "0x1", "1x1:0", "1x1:x"] {
+            assert!(Request::parse(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn packs_a_full_plate() {
+        let list = requests("2x2, 1x2:2, 1x1:4");
+        let plan = plan(4, 3, &list, true).unwrap();
+        assert!(plan.exact);
+        assert_packed(&plan, &list);
+    }
+
+    #[test]
+    fn turns_bins_that_only_fit_sideways() {
+        let list = requests("3x1");
+        let plan = plan(1, 3, &list, true).unwrap();
+        assert!(plan.bins[0].rotated);
+        assert_eq!((plan.bins[0].w, plan.bins[0].l), (1, 3));
+        assert!(super::plan(1, 3, &list, false).is_err());
+    }
+
+    #[test]
+    fn too_many_bins() {
+        let err = plan(2, 2, &requests("2x2, 1x1"), true).unwrap_err();
+        assert_eq!(err, "the bins cover 5 cells but the plate only has 4");
+        // the area fits but the shapes don't
+        assert!(plan(3, 3, &requests("2x2:2"), true).is_err());
+    }
+
+    #[test]
+    fn falls_back_to_first_fit() {
+        let list = requests("2x1:3, 1x1:2");
+        let plan = pack(4, 2, &list, true, 1).unwrap();
+        assert!(!plan.exact);
+        assert_packed(&plan, &list);
+        let err = pack(3, 3, &requests("2x2:2"), true, 1).unwrap_err();
+        assert_eq!(err, "no room left for a 2x2 bin");
+    }
+
+    #[test]
+    fn ascii_has_the_back_row_first() {
+        let plan = plan(2, 2, &requests("2x1"), false).unwrap();
+        assert_eq!(plan.ascii(), "..\nAA\n");
+    }
+}
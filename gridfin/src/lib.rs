@@ -0,0 +1,26 @@
+// gridfin as a library , the command line and the python bindings
+// are both built on it
+
+pub mod base;
+pub mod batch;
+pub mod cache;
+pub mod case;
+pub mod code;
+pub mod divider;
+pub mod drawing;
+pub mod export;
+pub mod gridfin;
+pub mod holder;
+pub mod layout;
+pub mod mask;
+pub mod outline;
+pub mod params;
+pub mod pattern;
+pub mod preview;
+pub mod profile;
+pub mod report;
+pub mod serve;
+pub mod shadow;
+pub mod step;
+pub mod text;
+pub mod tier;
@@ -1,66 +1,1056 @@
-use clap::Parser;
-use opencascade::primitives::Shape;
+use std::path::Path;
 
-mod gridfin;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use glam::dvec2;
+use opencascade::primitives::Shape;
 
-use crate::gridfin::{full, BasePlate, Plate,Connector};
+use gridfin::base::{BaseFloor, Bottom, Cleat, Hanger, Keyholes, Mount, Placement, Screws};
+use gridfin::case::{Case, Hinge};
+use gridfin::code::Code;
+use gridfin::divider::{positions, Axis, Divider};
+use gridfin::drawing::{Drawing, Hole};
+use gridfin::export::{parse_color, write, Body, Format};
+use gridfin::gridfin::{
+    filled, lid, lite, open, stack_height, total_height, units_for, BasePlate, BinOptions,
+    Connector, Walls,
+};
+use gridfin::holder::{Holder, Layout, Pocket};
+use gridfin::layout::{plan, Request};
+use gridfin::mask::Mask;
+use gridfin::pattern::{Pattern, Style};
+use gridfin::report::{Material, Report};
+use gridfin::shadow::{Shadow, ShadowSource};
+use gridfin::step::write_assembly;
+use gridfin::text::{Align, Label, Place};
+use gridfin::tier::{Floor, Tiers};
+use gridfin::{batch, cache, preview, serve};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// length of the unit
-    #[arg(short, long, default_value_t = 1)]
-    length: usize,
-    /// width of the unit
-    #[arg(short, long, default_value_t = 1)]
+    #[command(subcommand)]
+    command: Command,
+    /// folder for cached shapes, ~/.cache/gridfin by default
+    #[arg(long, global = true)]
+    shape_cache: Option<String>,
+    /// do not read or write cached shapes on disk
+    #[arg(long, global = true)]
+    no_cache: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// make a bin
+    Bin(BinArgs),
+    /// make a base plate for bins to sit in
+    Baseplate(BaseplateArgs),
+    /// make a lid that sits on a bin and takes more bins on top
+    Lid(PartArgs),
+    /// make a carrying case with a base plate floor and a hinged lid
+    Case(CaseArgs),
+    /// make the under side cells on their own
+    Connector(ConnectorArgs),
+    /// make every part listed in a json manifest
+    Batch(BatchArgs),
+    /// check bin settings or a manifest without making anything
+    Check(CheckArgs),
+    /// lay out bins on a drawer base plate
+    Plan(PlanArgs),
+    /// run a local http server that makes parts from json settings
+    Serve {
+        /// address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        address: String,
+        /// folder for the finished parts
+        #[arg(long, default_value = "gridfin_cache")]
+        cache: String,
+    },
+}
+
+// sizes are counted in cells and units , none of them can be zero
+fn positive(text: &str) -> Result<usize, String> {
+    match text.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_owned()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[derive(Args, Debug)]
+struct Footprint {
+    /// width in cells
+    #[arg(short, long, default_value_t = 1, value_parser = positive)]
     width: usize,
-    /// height of the unit
-    #[arg(short, long, default_value_t = 1)]
-    depth: usize,
-    /// export a .step file or an .stl
+    /// length in cells
+    #[arg(short, long, default_value_t = 1, value_parser = positive)]
+    length: usize,
+    /// footprint from a cell mask, an ascii grid file or a list of cells "0,0;1,0;0,1"
     #[arg(short, long)]
-    step: bool,
-    /// make a base plate
+    mask: Option<String>,
+}
+
+impl Footprint {
+    // a plain rectangle unless a mask is given
+    fn mask(&self) -> Result<Mask, String> {
+        match &self.mask {
+            Some(spec) => Mask::load(spec).map_err(|e| format!("bad mask : {}", e)),
+            None => Ok(Mask::rect(self.width, self.length)),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct Estimate {
+    /// print the volume, filament weight and print time of the part
+    #[arg(long)]
+    report: bool,
+    /// write the report as json
+    #[arg(long)]
+    report_json: Option<String>,
+    /// filament density in g/cm3
+    #[arg(long, default_value_t = 1.24)]
+    density: f64,
+    /// layer height in mm for the print time
+    #[arg(long, default_value_t = 0.2)]
+    layer_height: f64,
+    /// volumetric flow in mm3/s for the print time
+    #[arg(long, default_value_t = 10.0)]
+    flow: f64,
+}
+
+impl Estimate {
+    // none unless a report is asked for
+    fn material(&self) -> Result<Option<Material>, String> {
+        if !self.report && self.report_json.is_none() {
+            return Ok(None);
+        }
+        let material = Material::new(self.density, self.layer_height, self.flow);
+        material
+            .check()
+            .map_err(|e| format!("bad report : {}", e))?;
+        Ok(Some(material))
+    }
+
+    fn write(&self, report: &Report) -> Result<(), String> {
+        if let Some(path) = &self.report_json {
+            println!("report : {:?}", path);
+            std::fs::write(path, report.json()).map_err(|e| format!("{}: {}", path, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+struct Output {
+    /// output format, 3mf keeps labels as separate bodies
+    #[arg(long, value_enum, default_value_t = Format::Stl)]
+    format: Format,
+    /// output file, named after the part if not given
     #[arg(short, long)]
-    base: bool,
+    output: Option<String>,
+    /// render a shaded isometric png of the part
+    #[arg(long)]
+    preview: Option<String>,
+    /// preview image size in pixels
+    #[arg(long, default_value_t = 512, value_parser = positive)]
+    preview_size: usize,
+    /// write a dimensioned svg drawing, top and front views
+    #[arg(long)]
+    drawing: Option<String>,
+    #[command(flatten)]
+    estimate: Estimate,
+}
+
+#[derive(Args, Debug)]
+struct PartArgs {
+    #[command(flatten)]
+    footprint: Footprint,
+    #[command(flatten)]
+    output: Output,
 }
+
+#[derive(Args, Debug)]
+struct BaseplateArgs {
+    #[command(flatten)]
+    footprint: Footprint,
+    /// what goes under the sockets, a skeleton floor keeps a cross shaped rib under each cell
+    #[arg(long, value_enum, default_value_t = Bottom::Open)]
+    bottom: Bottom,
+    /// floor thickness in mm under the sockets, raised to fit keyholes
+    #[arg(long, default_value_t = 4.0)]
+    bottom_thickness: f64,
+    /// countersunk screw holes through the floor
+    #[arg(long, value_enum)]
+    screws: Option<Placement>,
+    /// screw clearance hole diameter in mm
+    #[arg(long, default_value_t = 3.4)]
+    screw_diameter: f64,
+    /// screw head diameter in mm for the countersink
+    #[arg(long, default_value_t = 6.4)]
+    screw_head: f64,
+    /// hang the plate on a wall with a french cleat or keyholes on the back
+    #[arg(long, value_enum)]
+    mount: Option<Hanger>,
+    /// cleat bevel in degrees
+    #[arg(long, default_value_t = 45.0)]
+    cleat_angle: f64,
+    /// how far the cleat stands off the back in mm
+    #[arg(long, default_value_t = 6.0)]
+    cleat_thickness: f64,
+    /// cleat height in mm, from the top of the plate
+    #[arg(long, default_value_t = 20.0)]
+    cleat_height: f64,
+    /// distance between the two keyholes in mm, the outer columns if not given
+    #[arg(long)]
+    keyhole_spacing: Option<f64>,
+    /// screw head diameter in mm the keyholes take
+    #[arg(long, default_value_t = 8.0)]
+    keyhole_head: f64,
+    /// screw shank diameter in mm the keyholes slide on
+    #[arg(long, default_value_t = 4.0)]
+    keyhole_shank: f64,
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(Args, Debug)]
+struct CaseArgs {
+    #[command(flatten)]
+    footprint: Footprint,
+    /// height in units of the bins it carries
+    #[arg(short, long, default_value_t = 6, value_parser = positive)]
+    depth: usize,
+    /// room inside the lid above the bins in mm
+    #[arg(long, default_value_t = 5.0)]
+    lid_depth: f64,
+    /// wall, floor and lid thickness in mm
+    #[arg(long, default_value_t = 3.0)]
+    wall: f64,
+    /// print the hinge pin in place or leave a bore for a rod or filament
+    #[arg(long, value_enum, default_value_t = Hinge::PrintInPlace)]
+    hinge: Hinge,
+    /// hinge pin diameter in mm
+    #[arg(long, default_value_t = 3.0)]
+    pin: f64,
+    /// snap latches across the front
+    #[arg(long, default_value_t = 2)]
+    latches: usize,
+    /// leave the handle off the front
+    #[arg(long)]
+    no_handle: bool,
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Cell {
+    // the bin feet with magnet holes
+    Under,
+    // the bin feet without magnets
+    Plain,
+    // the pockets the feet sit in
+    Lip,
+}
+
+#[derive(Args, Debug)]
+struct ConnectorArgs {
+    #[command(flatten)]
+    footprint: Footprint,
+    /// which connector to make
+    #[arg(long, value_enum, default_value_t = Cell::Under)]
+    kind: Cell,
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(Args, Debug)]
+struct BinArgs {
+    #[command(flatten)]
+    footprint: Footprint,
+    /// height in units
+    #[arg(short, long, default_value_t = 1, value_parser = positive)]
+    depth: usize,
+    /// overall height in mm instead of units, the most whole units that fit
+    #[arg(long, conflicts_with = "depth")]
+    height_mm: Option<f64>,
+    /// measure --height-mm to the stacking line, without the lip
+    #[arg(long, requires = "height_mm")]
+    height_excludes_lip: bool,
+    /// cut a tool shadow out of a filled bin, a step, brep or stl model or an svg, dxf or x,y outline file.
+    /// models hang down from z = 0, their top face, which goes level with the top of the wall
+    #[arg(long)]
+    shadow: Option<String>,
+    /// shadow position from the bin center in mm
+    #[arg(long, num_args = 2, value_names = ["X", "Y"], allow_negative_numbers = true)]
+    shadow_pos: Option<Vec<f64>>,
+    /// shadow rotation about z in degrees
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    shadow_rotate: f64,
+    /// how deep the shadow and the finger hole sink into the bin in mm, models are cut off below it.
+    /// 10 for outlines and the model's own height by default
+    #[arg(long)]
+    shadow_depth: Option<f64>,
+    /// extra gap around the shadow in mm, 0.5 for outlines and none for models by default
+    #[arg(long)]
+    clearance: Option<f64>,
+    /// finger hole diameter in mm, placed beside the shadow
+    #[arg(long)]
+    finger: Option<f64>,
+    /// finger hole offset from the shadow origin in mm
+    #[arg(long, num_args = 2, value_names = ["X", "Y"], allow_negative_numbers = true)]
+    finger_pos: Option<Vec<f64>>,
+    /// fill the bin with an array of round or hex pockets
+    #[arg(long, value_enum)]
+    holder: Option<Pocket>,
+    /// pocket diameter in mm, across the flats for hex pockets
+    #[arg(long, default_value_t = 6.5)]
+    pocket_diameter: f64,
+    /// pocket depth in mm
+    #[arg(long, default_value_t = 10.0)]
+    pocket_depth: f64,
+    /// material between pockets in mm
+    #[arg(long, default_value_t = 1.5)]
+    spacing: f64,
+    /// pocket columns and rows, as many as fit if not given
+    #[arg(long, num_args = 2, value_names = ["COLS", "ROWS"], value_parser = positive)]
+    count: Option<Vec<usize>>,
+    /// pocket arrangement
+    #[arg(long, value_enum, default_value_t = Layout::Rect)]
+    layout: Layout,
+    /// divider slot positions across the bin in mm from the center, plates run along y
+    #[arg(long, allow_negative_numbers = true)]
+    slot_x: Vec<f64>,
+    /// divider slot positions along the bin in mm from the center, plates run along x
+    #[arg(long, allow_negative_numbers = true)]
+    slot_y: Vec<f64>,
+    /// how far the divider slots go into the walls in mm, up to 1 as the walls allow by default
+    #[arg(long)]
+    slot_depth: Option<f64>,
+    /// divider plate thickness in mm
+    #[arg(long, default_value_t = 1.2)]
+    divider_thickness: f64,
+    /// cut a hex or slot pattern through the outer walls
+    #[arg(long, value_enum)]
+    pattern: Option<Style>,
+    /// pattern hole size in mm, across the flats or slot width
+    #[arg(long, default_value_t = 8.0)]
+    cell: f64,
+    /// material between pattern holes in mm
+    #[arg(long, default_value_t = 2.0)]
+    strut: f64,
+    /// wall thickness in mm, 2.15 by default
+    #[arg(long)]
+    wall: Option<f64>,
+    /// extra floor thickness in mm above the base
+    #[arg(long)]
+    floor: Option<f64>,
+    /// outside corner radius in mm, 3.75 by default
+    #[arg(long)]
+    fillet: Option<f64>,
+    /// radius along the bottom of the cavity in mm, 0.8 by default
+    #[arg(long)]
+    cavity_fillet: Option<f64>,
+    /// make a hollow thin shelled bin for vase mode or fast printing
+    #[arg(long)]
+    lite: bool,
+    /// shell thickness in mm for lite bins
+    #[arg(long, default_value_t = 0.8)]
+    shell: f64,
+    /// stepped or sloped floor, rising from the front to the back
+    #[arg(long, value_enum)]
+    tiers: Option<Floor>,
+    /// number of steps for a stepped floor
+    #[arg(long, default_value_t = 3)]
+    tier_count: usize,
+    /// height of each step, or of the whole slope, in mm
+    #[arg(long, default_value_t = 7.0)]
+    tier_rise: f64,
+    /// text label for the bin
+    #[arg(long)]
+    label: Option<String>,
+    /// truetype font file for the label
+    #[arg(long)]
+    font: Option<String>,
+    /// label font size in mm
+    #[arg(long, default_value_t = 6.0)]
+    label_size: f64,
+    /// label depth in mm
+    #[arg(long, default_value_t = 0.6)]
+    label_depth: f64,
+    /// where the label goes
+    #[arg(long, value_enum, default_value_t = Place::Floor)]
+    label_place: Place,
+    /// label alignment
+    #[arg(long, value_enum, default_value_t = Align::Center)]
+    label_align: Align,
+    /// raise the label instead of engraving it
+    #[arg(long)]
+    emboss: bool,
+    /// label and code colour for 3mf output, rrggbb
+    #[arg(long, default_value = "000000")]
+    label_color: String,
+    /// put a qr code of this text on the bin, for scanning into an inventory
+    #[arg(long)]
+    code: Option<String>,
+    /// size of one code module in mm
+    #[arg(long, default_value_t = 1.0)]
+    code_module: f64,
+    /// code depth in mm
+    #[arg(long, default_value_t = 0.6)]
+    code_depth: f64,
+    /// where the code goes, the floor or the bottom
+    #[arg(long, value_enum, default_value_t = Place::Floor)]
+    code_place: Place,
+    /// code position from the middle of the floor or the bottom cell in mm
+    #[arg(long, num_args = 2, value_names = ["X", "Y"], allow_negative_numbers = true)]
+    code_pos: Option<Vec<f64>>,
+    /// raise the code instead of engraving it
+    #[arg(long)]
+    code_emboss: bool,
+    /// bin colour for 3mf output, rrggbb
+    #[arg(long, default_value = "808080")]
+    color: String,
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(Args, Debug)]
+struct BatchArgs {
+    /// json list of parts, each with the settings the server takes and an optional name
+    manifest: String,
+    /// folder for the finished parts
+    #[arg(short, long, default_value = ".")]
+    out: String,
+    #[command(flatten)]
+    estimate: Estimate,
+}
+
+#[derive(Args, Debug)]
+struct CheckArgs {
+    /// check a batch manifest instead of the bin settings
+    #[arg(long)]
+    manifest: Option<String>,
+    #[command(flatten)]
+    bin: BinArgs,
+}
+
+#[derive(Args, Debug)]
+struct PlanArgs {
+    /// base plate width in cells
+    #[arg(short, long, value_parser = positive)]
+    width: usize,
+    /// base plate length in cells
+    #[arg(short, long, value_parser = positive)]
+    length: usize,
+    /// the bins, "WxL[xH][:count],..."
+    bins: String,
+    /// keep planned bins the way they are given
+    #[arg(long)]
+    no_rotate: bool,
+}
+
+// a bin's settings , checked but not built yet
+struct Setup {
+    prefix: String,
+    // height in units
+    depth: usize,
+    shadow: Option<Shadow>,
+    holder: Option<Holder>,
+    options: BinOptions,
+    label: Option<Label>,
+    code: Option<Code>,
+    color: [u8; 3],
+    label_color: [u8; 3],
+}
+
 fn main() {
     let cli = Cli::parse();
+    cache::set_dir(match &cli.shape_cache {
+        _ if cli.no_cache => None,
+        Some(dir) => Some(dir.into()),
+        None => cache::default_dir(),
+    });
     println!("generate");
     println!("{:#?}", cli);
-    let prefix: String;
-    let f: Shape;
-    // Is it a base plate ?
-    if cli.base {
-        let mut bp = BasePlate::new(cli.width, cli.length);
-        f = bp.shape();
-        prefix = "base".to_owned();
+    let result = match &cli.command {
+        Command::Bin(args) => bin(args),
+        Command::Baseplate(args) => baseplate(args),
+        Command::Lid(args) => make_lid(args),
+        Command::Case(args) => make_case(args),
+        Command::Connector(args) => connector(args),
+        Command::Batch(args) => run_batch(args),
+        Command::Check(args) => check(args),
+        Command::Plan(args) => drawer(args),
+        Command::Serve {
+            address,
+            cache: dir,
+        } => serve::run(address, dir),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+// everything that goes into a bin , checked before anything is built
+fn prepare(args: &BinArgs, mask: &Mask) -> Result<Setup, String> {
+    let color = parse_color(&args.color)?;
+    let label_color = parse_color(&args.label_color)?;
+    let depth = match args.height_mm {
+        Some(mm) => {
+            units_for(mm, !args.height_excludes_lip).map_err(|e| format!("bad height : {}", e))?
+        }
+        None => args.depth,
+    };
+    let mut setup = Setup {
+        prefix: "gf".to_owned(),
+        depth,
+        shadow: None,
+        holder: None,
+        options: BinOptions::default(),
+        label: None,
+        code: None,
+        color,
+        label_color,
+    };
+    // wall sizes are for open bins
+    let sizes = [args.wall, args.floor, args.fillet, args.cavity_fillet];
+    let sized = sizes.iter().any(|w| w.is_some());
+    if sized && (args.lite || args.shadow.is_some() || args.holder.is_some()) {
+        return Err("wall , floor and fillet sizes are for open bins".to_owned());
+    }
+    if args.lite {
+        if args.shadow.is_some()
+            || args.holder.is_some()
+            || args.pattern.is_some()
+            || args.tiers.is_some()
+            || !args.slot_x.is_empty()
+            || !args.slot_y.is_empty()
+        {
+            return Err("lite bins are plain shells , no interiors , slots or patterns".to_owned());
+        }
+        if args.shell <= 0.0 {
+            return Err("bad shell : thickness must be more than zero".to_owned());
+        }
+        setup.prefix = "lite".to_owned();
+        // the shell is the wall , labels on the front go into it
+        setup.options.walls.thickness = args.shell;
+    }
+    if let Some(path) = &args.shadow {
+        let source = ShadowSource::load(path).map_err(|e| format!("bad shadow : {}", e))?;
+        let mut shadow = Shadow::new(source);
+        if let Some(pos) = &args.shadow_pos {
+            shadow.pos = dvec2(pos[0], pos[1]);
+        }
+        shadow.rotation = args.shadow_rotate;
+        shadow.depth = args.shadow_depth;
+        if let Some(clearance) = args.clearance {
+            shadow.clearance = clearance;
+        }
+        if let Some(diameter) = args.finger {
+            let offset = match &args.finger_pos {
+                Some(pos) => dvec2(pos[0], pos[1]),
+                None => dvec2(0.0, 0.0),
+            };
+            shadow.finger = Some((diameter, offset));
+        }
+        shadow
+            .check(mask, depth)
+            .map_err(|e| format!("bad shadow : {}", e))?;
+        setup.shadow = Some(shadow);
+        setup.prefix = "shadow".to_owned();
+    }
+    if let Some(pocket) = args.holder {
+        let mut holder = Holder::new(pocket, args.pocket_diameter, args.pocket_depth);
+        holder.layout = args.layout;
+        holder.spacing = args.spacing;
+        if let Some(count) = &args.count {
+            holder.count = Some((count[0], count[1]));
+        }
+        holder
+            .check(mask, depth)
+            .map_err(|e| format!("bad holder : {}", e))?;
+        setup.holder = Some(holder);
+        setup.prefix = "holder".to_owned();
+    }
+    // the extras only go in open bins
+    if setup.shadow.is_none() && setup.holder.is_none() {
+        let mut walls = Walls::default();
+        walls.thickness = args.wall.unwrap_or(walls.thickness);
+        walls.floor = args.floor.unwrap_or(walls.floor);
+        walls.fillet = args.fillet.unwrap_or(walls.fillet);
+        walls.cavity_fillet = args.cavity_fillet.unwrap_or(walls.cavity_fillet);
+        walls
+            .check(depth)
+            .map_err(|e| format!("bad walls : {}", e))?;
+        setup.options.walls = walls;
+        let mut slots = positions(Axis::X, &args.slot_x);
+        slots.extend(positions(Axis::Y, &args.slot_y));
+        if !slots.is_empty() {
+            let mut d = Divider::new(slots);
+            d.thickness = args.divider_thickness;
+            d.slot_depth = args
+                .slot_depth
+                .unwrap_or_else(|| Divider::slot_depth_for(&walls));
+            d.check(mask, &walls)
+                .map_err(|e| format!("bad divider : {}", e))?;
+            setup.prefix = "slotted".to_owned();
+            setup.options.divider = Some(d);
+        }
+        if let Some(style) = args.pattern {
+            // the holes are laid out on plain walls
+            if setup.options.divider.is_some() || args.tiers.is_some() {
+                return Err("patterned walls can't have divider slots or tiers".to_owned());
+            }
+            let mut p = Pattern::new(style);
+            p.cell = args.cell;
+            p.strut = args.strut;
+            p.check().map_err(|e| format!("bad pattern : {}", e))?;
+            setup.prefix.push_str("_pattern");
+            setup.options.pattern = Some(p);
+        }
+        if let Some(floor) = args.tiers {
+            // divider plates are cut for a flat floor
+            if setup.options.divider.is_some() {
+                return Err("tiered bins can't have divider slots".to_owned());
+            }
+            let t = Tiers::new(floor, args.tier_count, args.tier_rise);
+            t.check(&walls, depth)
+                .map_err(|e| format!("bad tiers : {}", e))?;
+            setup.prefix.push_str("_tiered");
+            setup.options.tiers = Some(t);
+        }
+    }
+    // the floor is only at walls.bottom() in a plain open bin , labels
+    // and codes on anything else would float or sink into the shell
+    let plain = !args.lite
+        && setup.shadow.is_none()
+        && setup.holder.is_none()
+        && setup.options.tiers.is_none();
+    if let Some(text) = &args.label {
+        let Some(font) = &args.font else {
+            return Err("a label needs a --font".to_owned());
+        };
+        let mut label = Label::new(text, font);
+        label.size = args.label_size;
+        label.depth = args.label_depth;
+        label.place = args.label_place;
+        label.align = args.label_align;
+        label.emboss = args.emboss;
+        if label.place == Place::Floor && !plain {
+            return Err(
+                "floor labels need a plain open bin , not a lite , filled or tiered one".to_owned(),
+            );
+        }
+        // the shelf is built onto the walls and would block the slots
+        if label.place == Place::Shelf && (!plain || setup.options.divider.is_some()) {
+            return Err("label shelves need a plain open bin , without divider slots".to_owned());
+        }
+        // lite feet are a thin hollow shell
+        if label.place == Place::Bottom && args.lite {
+            return Err("lite bins can't have labels underneath".to_owned());
+        }
+        label
+            .check(mask, &setup.options.walls, depth)
+            .map_err(|e| format!("bad label : {}", e))?;
+        setup.label = Some(label);
+    }
+    if let Some(text) = &args.code {
+        let mut code = Code::new(text);
+        code.module = args.code_module;
+        code.depth = args.code_depth;
+        code.place = args.code_place;
+        if let Some(pos) = &args.code_pos {
+            code.pos = dvec2(pos[0], pos[1]);
+        }
+        code.emboss = args.code_emboss;
+        if code.place == Place::Floor && !plain {
+            return Err(
+                "floor codes need a plain open bin , not a lite , filled or tiered one".to_owned(),
+            );
+        }
+        // lite feet are a thin hollow shell
+        if code.place == Place::Bottom && args.lite {
+            return Err("lite bins can't have codes underneath".to_owned());
+        }
+        code.check(mask, &setup.options.walls)
+            .map_err(|e| format!("bad code : {}", e))?;
+        setup.code = Some(code);
+    }
+    if !mask.is_rect() {
+        setup.prefix.push_str("_mask");
+    }
+    Ok(setup)
+}
+
+// the heights of the bin , and the usable depth inside an open one
+fn heights(setup: &Setup, lite: bool) -> String {
+    let mut text = format!(
+        "height : {} u , {:.2} mm to stack , {:.2} mm with the lip",
+        setup.depth,
+        stack_height(setup.depth),
+        total_height(setup.depth)
+    );
+    if !lite && setup.shadow.is_none() && setup.holder.is_none() {
+        let inside = setup.options.walls.depth(setup.depth);
+        text.push_str(&format!(" , {:.2} mm inside", inside));
+    }
+    text
+}
+
+fn bin(args: &BinArgs) -> Result<(), String> {
+    let mask = args.footprint.mask()?;
+    let setup = prepare(args, &mask)?;
+    let depth = setup.depth;
+    println!("{}", heights(&setup, args.lite));
+    let format = args.output.format;
+    let mut f: Shape;
+    // extra parts to go with the main one
+    let mut extras: Vec<(String, Shape)> = Vec::new();
+    // bodies printed along with the main one
+    let mut inlays: Vec<Body> = Vec::new();
+    let mut drawing = Drawing::new(&mask, Some(depth));
+    // a hollow shell
+    if args.lite {
+        f = lite(&mask, depth, args.shell);
+        drawing.magnets = false;
+        // the shell is the wall , and the cavity runs down into the feet
+        drawing.walls = setup.options.walls;
+        drawing.walls.floor = 0.0;
+    // interiors cut out of a solid bin
+    } else if setup.shadow.is_some() || setup.holder.is_some() {
+        drawing.open = false;
+        let mut cutters = Vec::new();
+        if let Some(mut shadow) = setup.shadow {
+            if let Some((diameter, pos)) = shadow.finger_hole() {
+                drawing.holes.push(Hole::new("finger", pos, diameter));
+            }
+            let cutter = shadow
+                .shape(&mask, depth)
+                .map_err(|e| format!("bad shadow : {}", e))?;
+            cutters.push(cutter);
+        }
+        if let Some(mut holder) = setup.holder {
+            let cutter = holder
+                .shape(&mask, depth)
+                .map_err(|e| format!("bad holder : {}", e))?;
+            let kind = match holder.pocket {
+                Pocket::Round => "pocket",
+                Pocket::Hex => "hex pocket",
+            };
+            let centers = holder.centers(&mask);
+            println!("{} {}s", centers.len(), kind);
+            for center in centers {
+                drawing.holes.push(Hole::new(kind, center, holder.diameter));
+            }
+            cutters.push(cutter);
+        }
+        f = filled(&mask, depth, cutters);
     // make an basic module
     } else {
-        //let mut pl = Connector::new(cli.width,cli.length,Connector::UNDER);
-        //f = pl.shape();
-        f = full(cli.width, cli.length, cli.depth);
-        prefix = "gf".to_owned();
-    }
-    // save the generated build
-    let ext: String;
-    if cli.step {
-        ext = "step".to_owned();
-    } else {
-        ext = "stl".to_owned();
+        if let Some(d) = &setup.options.divider {
+            for (length, plate) in d.plates(&mask, &setup.options.walls, depth) {
+                extras.push((format!("divider_{:.1}x{}", length, depth), plate));
+            }
+            drawing.divider = Some(d.clone());
+        }
+        drawing.walls = setup.options.walls;
+        f = open(&mask, depth, &setup.options);
+        if let Some(shelf) = setup
+            .label
+            .as_ref()
+            .and_then(|l| l.shelf(&mask, &setup.options.walls, depth))
+        {
+            f = f.union(&shelf).into();
+        }
+    }
+    // a 3mf keeps the letters and the code as their own bodies
+    // so they can be printed in another colour
+    let keep = format == Format::ThreeMf;
+    // put the label on
+    if let Some(label) = &setup.label {
+        let letters = label
+            .shape(&mask, &setup.options.walls, depth)
+            .map_err(|e| format!("bad label : {}", e))?;
+        let inlay;
+        (f, inlay) = mark(f, letters, label.emboss, keep);
+        if let Some(shape) = inlay {
+            let mut body = Body::new("label", shape);
+            body.color = Some(setup.label_color);
+            inlays.push(body);
+        }
     }
-    // make a working filename
+    // and the code
+    if let Some(code) = &setup.code {
+        let modules = code
+            .shape(&mask, &setup.options.walls)
+            .map_err(|e| format!("bad code : {}", e))?;
+        let inlay;
+        (f, inlay) = mark(f, modules, code.emboss, keep);
+        if let Some(shape) = inlay {
+            let mut body = Body::new("code", shape);
+            body.color = Some(setup.label_color);
+            inlays.push(body);
+        }
+    }
+    let prefix = setup.prefix;
     let name = format!(
         "{}_{}x{}x{}.{}",
-        prefix, cli.width, cli.length, cli.depth, ext
+        prefix,
+        mask.width(),
+        mask.height(),
+        depth,
+        format.ext()
     );
-    // Build the file
-    // this needs STL resolution
+    let mut body = Body::new(&prefix, f);
+    body.color = Some(setup.color);
+    let mut bodies = vec![body];
+    bodies.extend(inlays);
+    drawing.title = prefix.clone();
+    finish(&name, bodies, extras, Some(drawing), &args.output)
+}
+
+// raise `mark` from the part or cut it in , the mark comes back
+// as its own body to keep if asked
+fn mark(part: Shape, mark: Shape, emboss: bool, keep: bool) -> (Shape, Option<Shape>) {
+    match (emboss, keep) {
+        (true, true) => (part, Some(mark)),
+        (true, false) => (part.union(&mark).into(), None),
+        (false, true) => {
+            let inlay = part.intersect(&mark).into();
+            (part.subtract(&mark).into(), Some(inlay))
+        }
+        (false, false) => (part.subtract(&mark).into(), None),
+    }
+}
+
+fn baseplate(args: &BaseplateArgs) -> Result<(), String> {
+    let mask = args.footprint.mask()?;
+    let mut floor = BaseFloor::new(args.bottom);
+    floor.thickness = args.bottom_thickness;
+    let mount = match args.mount {
+        Some(Hanger::Cleat) => Some(Mount::Cleat(Cleat {
+            angle: args.cleat_angle,
+            thickness: args.cleat_thickness,
+            height: args.cleat_height,
+        })),
+        Some(Hanger::Keyhole) => Some(Mount::Keyholes(Keyholes {
+            spacing: args.keyhole_spacing,
+            head: args.keyhole_head,
+            shank: args.keyhole_shank,
+            ..Default::default()
+        })),
+        None => None,
+    };
+    // keyholes can change the floor , so name it afterwards
+    if let Some(mount) = mount {
+        floor.set_mount(mount);
+    }
+    let mut prefix = match floor.bottom {
+        Bottom::Open => "base".to_owned(),
+        Bottom::Solid => "base_solid".to_owned(),
+        Bottom::Skeleton => "base_skeleton".to_owned(),
+    };
+    let mut drawing = Drawing::new(&mask, None);
+    if let Some(placement) = args.screws {
+        let mut screws = Screws::new(placement);
+        screws.diameter = args.screw_diameter;
+        screws.head = args.screw_head;
+        for center in screws.centers(&mask) {
+            drawing.holes.push(Hole::new("screw", center, screws.head));
+        }
+        floor.screws = Some(screws);
+        prefix.push_str("_screw");
+    }
+    match args.mount {
+        Some(Hanger::Cleat) => prefix.push_str("_cleat"),
+        Some(Hanger::Keyhole) => prefix.push_str("_keyhole"),
+        None => {}
+    }
+    floor
+        .check(&mask)
+        .map_err(|e| format!("bad base : {}", e))?;
+    drawing.base_floor = floor.height();
+    drawing.title = prefix.clone();
+    let mut plate = BasePlate::from_mask(&mask);
+    plate.floor = floor;
+    let f = plate.shape();
+    let name = part_name(&prefix, &mask, args.output.format);
+    finish(
+        &name,
+        vec![Body::new(&prefix, f)],
+        Vec::new(),
+        Some(drawing),
+        &args.output,
+    )
+}
+
+fn make_lid(args: &PartArgs) -> Result<(), String> {
+    let mask = args.footprint.mask()?;
+    let f = lid(&mask);
+    let name = part_name("lid", &mask, args.output.format);
+    finish(
+        &name,
+        vec![Body::new("lid", f)],
+        Vec::new(),
+        None,
+        &args.output,
+    )
+}
+
+fn make_case(args: &CaseArgs) -> Result<(), String> {
+    let mask = args.footprint.mask()?;
+    let mut case = Case::from_mask(&mask, args.depth);
+    case.lid_depth = args.lid_depth;
+    case.wall = args.wall;
+    case.hinge = args.hinge;
+    case.pin = args.pin;
+    case.latches = args.latches;
+    case.handle = !args.no_handle;
+    let (body, top) = case.shape().map_err(|e| format!("bad case : {}", e))?;
+    println!(
+        "case : {:.1} mm tall open , {:.1} mm closed",
+        case.height(),
+        case.height() + case.lid_height()
+    );
+    let name = part_name("case", &mask, args.output.format);
+    finish(
+        &name,
+        vec![Body::new("case", body), Body::new("lid", top)],
+        Vec::new(),
+        None,
+        &args.output,
+    )
+}
+
+fn connector(args: &ConnectorArgs) -> Result<(), String> {
+    let mask = args.footprint.mask()?;
+    let (prefix, config) = match args.kind {
+        Cell::Under => ("connector_under", Connector::UNDER),
+        Cell::Plain => ("connector_plain", Connector::PLAIN),
+        Cell::Lip => ("connector_lip", Connector::LIP),
+    };
+    let f = Connector::from_mask(&mask, config).shape();
+    let name = part_name(prefix, &mask, args.output.format);
+    finish(
+        &name,
+        vec![Body::new(prefix, f)],
+        Vec::new(),
+        None,
+        &args.output,
+    )
+}
+
+// "prefix_WxL.ext" , marked when the footprint is not a rectangle
+fn part_name(prefix: &str, mask: &Mask, format: Format) -> String {
+    let marker = if mask.is_rect() { "" } else { "_mask" };
+    format!(
+        "{}{}_{}x{}.{}",
+        prefix,
+        marker,
+        mask.width(),
+        mask.height(),
+        format.ext()
+    )
+}
+
+// write the part and everything asked for along with it
+fn finish(
+    name: &str,
+    bodies: Vec<Body>,
+    extras: Vec<(String, Shape)>,
+    drawing: Option<Drawing>,
+    output: &Output,
+) -> Result<(), String> {
+    let name = output.output.as_deref().unwrap_or(name);
+    let format = output.format;
     println!("output : {:?}", name);
-    if cli.step {
-        f.write_step(name).unwrap();
-    } else {
-        f.write_stl_with_tolerance(name, 0.1).unwrap();
-        //f.write_stl(name).unwrap();
+    if let Some(material) = output.estimate.material()? {
+        let report = Report::new(name, &bodies, &material);
+        println!("{}", report.text());
+        output.estimate.write(&report)?;
+    }
+    if let Some(path) = &output.drawing {
+        let Some(drawing) = &drawing else {
+            return Err("drawings are made for bins and base plates".to_owned());
+        };
+        println!("drawing : {:?}", path);
+        drawing.write(path)?;
     }
+    if let Some(path) = &output.preview {
+        println!("preview : {:?}", path);
+        let image = preview::render(&bodies, output.preview_size, output.preview_size);
+        image.write_png(path)?;
+    }
+    write(name, format, bodies)?;
+    // extras go beside the main output
+    let dir = Path::new(name).parent().unwrap_or(Path::new(""));
+    for (extra_prefix, shape) in extras {
+        let path = dir.join(format!("{}.{}", extra_prefix, format.ext()));
+        let name = path.to_string_lossy();
+        println!("output : {:?}", name);
+        write(&name, format, vec![Body::new(&extra_prefix, shape)])?;
+    }
+    Ok(())
+}
+
+fn run_batch(args: &BatchArgs) -> Result<(), String> {
+    let parts = batch::load(&args.manifest)?;
+    let material = args.estimate.material()?;
+    let reports = batch::run(&parts, &args.out, material.as_ref())?;
+    if material.is_some() {
+        let total = Report::total("total", &reports);
+        println!("{}", total.text());
+        if let Some(path) = &args.estimate.report_json {
+            println!("report : {:?}", path);
+            let json = serde_json::json!({ "parts": reports, "total": total });
+            std::fs::write(path, serde_json::to_string_pretty(&json).unwrap())
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+    }
+    Ok(())
+}
+
+// report problems without building anything
+fn check(args: &CheckArgs) -> Result<(), String> {
+    match &args.manifest {
+        Some(path) => {
+            let parts = batch::load(path)?;
+            batch::check(&parts)?;
+            println!("ok : {} parts", parts.len());
+        }
+        None => {
+            let mask = args.bin.footprint.mask()?;
+            let setup = prepare(&args.bin, &mask)?;
+            if let Some(label) = &setup.label {
+                if !Path::new(&label.font).exists() {
+                    return Err(format!("bad label : no font at {:?}", label.font));
+                }
+            }
+            args.bin.output.estimate.material()?;
+            println!("{}", heights(&setup, args.bin.lite));
+            println!("ok : {}", setup.prefix);
+        }
+    }
+    Ok(())
+}
+
+// lay out a drawer and write the map , the json and the model
+fn drawer(args: &PlanArgs) -> Result<(), String> {
+    let requests = Request::parse_list(&args.bins).map_err(|e| format!("bad plan : {}", e))?;
+    let layout = plan(args.width, args.length, &requests, !args.no_rotate)
+        .map_err(|e| format!("bad plan : {}", e))?;
+    print!("{}", layout.ascii());
+    let base = format!("plan_{}x{}", args.width, args.length);
+    let files = [
+        (format!("{}.txt", base), layout.ascii()),
+        (format!("{}.json", base), layout.json()),
+    ];
+    for (name, text) in files {
+        println!("output : {:?}", name);
+        std::fs::write(&name, text).map_err(|e| format!("{}: {}", name, e))?;
+    }
+    let name = format!("{}.step", base);
+    println!("output : {:?}", name);
+    write_assembly(&name, &base, layout.parts())
 }
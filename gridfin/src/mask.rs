@@ -0,0 +1,468 @@
+// Cell masks for non rectangular footprints
+// a mask is a grid of occupied cells , the outline of the
+// bin or base plate follows the union of the occupied cells
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use glam::{dvec2, dvec3, DVec2};
+use opencascade::primitives::{Edge, Wire};
+
+use crate::gridfin::SIZE;
+
+#[derive(Debug, Clone)]
+pub struct Mask {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl Mask {
+    // radius of the inner (concave) corners on the outside outline
+    // insets grow this so the offset outlines stay parallel
+    pub const CONCAVE_FILLET: f64 = 0.8;
+
+    // a full rectangle of cells
+    pub fn rect(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![true; width * height],
+        }
+    }
+
+    // ascii grid , one line per row , first line is the back (max y)
+    // `#`, `x`, `X` or `1` is occupied , anything else is empty
+    pub fn from_ascii(text: &str) -> Result<Self, String> {
+        let rows: Vec<&str> = text
+            .lines()
+            .map(|l| l.trim_end())
+            .filter(|l| !l.is_empty())
+            .collect();
+        let height = rows.len();
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        let mut mask = Self {
+            width,
+            height,
+            cells: vec![false; width * height],
+        };
+        for (row, line) in rows.iter().enumerate() {
+            let y = height - 1 - row;
+            for (x, c) in line.chars().enumerate() {
+                if matches!(c, '#' | 'x' | 'X' | '1') {
+                    mask.set(x, y);
+                }
+            }
+        }
+        mask.validate()?;
+        Ok(mask)
+    }
+
+    // list of cells as "x,y" pairs seperated by `;` or whitespace
+    pub fn from_cells(text: &str) -> Result<Self, String> {
+        let mut cells = Vec::new();
+        for pair in text.split(|c: char| c == ';' || c.is_whitespace()) {
+            if pair.is_empty() {
+                continue;
+            }
+            let (x, y) = pair
+                .split_once(',')
+                .ok_or(format!("bad cell {:?} , expected x,y", pair))?;
+            let x: usize = x.trim().parse().map_err(|_| format!("bad cell {:?}", pair))?;
+            let y: usize = y.trim().parse().map_err(|_| format!("bad cell {:?}", pair))?;
+            cells.push((x, y));
+        }
+        let width = cells.iter().map(|c| c.0 + 1).max().unwrap_or(0);
+        let height = cells.iter().map(|c| c.1 + 1).max().unwrap_or(0);
+        let mut mask = Self {
+            width,
+            height,
+            cells: vec![false; width * height],
+        };
+        for (x, y) in cells {
+            mask.set(x, y);
+        }
+        mask.validate()?;
+        Ok(mask)
+    }
+
+    // a file is read as an ascii grid , otherwise it is a cell list
+    pub fn load(spec: &str) -> Result<Self, String> {
+        if Path::new(spec).is_file() {
+            let text = fs::read_to_string(spec).map_err(|e| format!("{}: {}", spec, e))?;
+            Self::from_ascii(&text)
+        } else {
+            Self::from_cells(spec)
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_rect(&self) -> bool {
+        self.cells.iter().all(|c| *c)
+    }
+
+    pub fn occupied(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.cells[y * self.width + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.cells[y * self.width + x] = true;
+    }
+
+    // occupied cells
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.height)
+            .flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+            .filter(|(x, y)| self.occupied(*x, *y))
+    }
+
+    // center of a cell , the origin is the center of the bounding box
+    pub fn cell_center(&self, x: usize, y: usize) -> DVec2 {
+        dvec2(
+            (SIZE * x as f64) - (SIZE * (self.width - 1) as f64) / 2.0,
+            (SIZE * y as f64) - (SIZE * (self.height - 1) as f64) / 2.0,
+        )
+    }
+
+    // is a point ( in mm from the center ) over an occupied cell
+    pub fn contains(&self, p: DVec2) -> bool {
+        let x = p.x / SIZE + self.width as f64 / 2.0;
+        let y = p.y / SIZE + self.height as f64 / 2.0;
+        x >= 0.0 && y >= 0.0 && self.occupied(x as usize, y as usize)
+    }
+
+    // the outline must be a single loop , so the cells have to
+    // be joined by edges and not enclose any empty cells
+    fn validate(&self) -> Result<(), String> {
+        let count = self.cells().count();
+        if count == 0 {
+            return Err("mask has no cells".to_owned());
+        }
+        // flood the occupied cells
+        let start = self.cells().next().unwrap();
+        let mut seen = vec![false; self.width * self.height];
+        let mut stack = vec![start];
+        let mut reached = 0;
+        while let Some((x, y)) = stack.pop() {
+            if !self.occupied(x, y) || seen[y * self.width + x] {
+                continue;
+            }
+            seen[y * self.width + x] = true;
+            reached += 1;
+            stack.push((x + 1, y));
+            stack.push((x, y + 1));
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+        }
+        if reached != count {
+            return Err("mask cells are not all joined together".to_owned());
+        }
+        // flood the empty cells from a one cell border
+        let (w, h) = (self.width + 2, self.height + 2);
+        let mut outside = vec![false; w * h];
+        let mut stack = vec![(0usize, 0usize)];
+        while let Some((x, y)) = stack.pop() {
+            if x >= w || y >= h || outside[y * w + x] {
+                continue;
+            }
+            if x > 0 && y > 0 && self.occupied(x - 1, y - 1) {
+                continue;
+            }
+            outside[y * w + x] = true;
+            stack.push((x + 1, y));
+            stack.push((x, y + 1));
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+        }
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !self.occupied(x, y) && !outside[(y + 1) * w + x + 1] {
+                    return Err(format!("mask has a hole at {},{}", x, y));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // corners of the outline in grid units , counter clockwise
+    fn corners(&self) -> Vec<(i64, i64)> {
+        // every cell edge that is not shared with another cell ,
+        // walked counter clockwise around each cell
+        let mut next: HashMap<(i64, i64), (i64, i64)> = HashMap::new();
+        for (x, y) in self.cells() {
+            let (xi, yi) = (x as i64, y as i64);
+            if y == 0 || !self.occupied(x, y - 1) {
+                next.insert((xi, yi), (xi + 1, yi));
+            }
+            if !self.occupied(x + 1, y) {
+                next.insert((xi + 1, yi), (xi + 1, yi + 1));
+            }
+            if !self.occupied(x, y + 1) {
+                next.insert((xi + 1, yi + 1), (xi, yi + 1));
+            }
+            if x == 0 || !self.occupied(x - 1, y) {
+                next.insert((xi, yi + 1), (xi, yi));
+            }
+        }
+        // chain them up
+        let start = *next.keys().min().unwrap();
+        let mut path = vec![start];
+        let mut current = next[&start];
+        while current != start {
+            path.push(current);
+            current = next[&current];
+        }
+        // drop the points along the straight runs
+        let n = path.len();
+        (0..n)
+            .filter(|i| {
+                let p = path[(i + n - 1) % n];
+                let c = path[*i];
+                let q = path[(i + 1) % n];
+                (c.0 - p.0) * (q.1 - c.1) - (c.1 - p.1) * (q.0 - c.0) != 0
+            })
+            .map(|i| path[i])
+            .collect()
+    }
+
+    // the outline polygon in mm moved inwards by `inset`
+    pub fn polygon(&self, inset: f64) -> Vec<DVec2> {
+        let corners: Vec<DVec2> = self
+            .corners()
+            .iter()
+            .map(|(x, y)| {
+                dvec2(
+                    SIZE * (*x as f64 - self.width as f64 / 2.0),
+                    SIZE * (*y as f64 - self.height as f64 / 2.0),
+                )
+            })
+            .collect();
+        let n = corners.len();
+        (0..n)
+            .map(|i| {
+                let prev = corners[(i + n - 1) % n];
+                let c = corners[i];
+                let next = corners[(i + 1) % n];
+                // inward normals of the two edges ( left of a ccw edge )
+                let n1 = (c - prev).normalize().perp();
+                let n2 = (next - c).normalize().perp();
+                c + inset * (n1 + n2)
+            })
+            .collect()
+    }
+
+    // is the box from `min` to `max` inside the outline moved inwards
+    // by `inset` , no edge may cross it since the corners alone
+    // could sit either side of a notch
+    pub fn holds(&self, inset: f64, min: DVec2, max: DVec2) -> bool {
+        let polygon = self.polygon(inset);
+        let n = polygon.len();
+        // the edges all run along x or y
+        let crossed = (0..n).any(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+            a.x.min(b.x) < max.x
+                && a.x.max(b.x) > min.x
+                && a.y.min(b.y) < max.y
+                && a.y.max(b.y) > min.y
+        });
+        !crossed && inside((min + max) / 2.0, &polygon)
+    }
+
+    // the filleted outline wire on the xy plane
+    // outside corners get `fillet` , inside corners grow with the inset
+    pub fn outline(&self, inset: f64, fillet: f64) -> Wire {
+        let points = self.polygon(inset);
+        let concave = Mask::CONCAVE_FILLET + inset;
+        let n = points.len();
+        let mut edges: Vec<Edge> = Vec::new();
+        let mut arcs: Vec<(DVec2, DVec2, DVec2)> = Vec::new();
+        for i in 0..n {
+            let prev = points[(i + n - 1) % n];
+            let c = points[i];
+            let next = points[(i + 1) % n];
+            let d1 = (c - prev).normalize();
+            let d2 = (next - c).normalize();
+            let convex = d1.perp_dot(d2) > 0.0;
+            let radius = if convex { fillet } else { concave };
+            let start = c - radius * d1;
+            let end = c + radius * d2;
+            let center = if convex {
+                c + radius * (d1.perp() + d2.perp())
+            } else {
+                c - radius * (d1.perp() + d2.perp())
+            };
+            let mid = center + radius * (c - center).normalize();
+            arcs.push((start, mid, end));
+        }
+        for i in 0..n {
+            let (start, mid, end) = arcs[i];
+            let next_start = arcs[(i + 1) % n].0;
+            edges.push(Edge::arc(
+                dvec3(start.x, start.y, 0.0),
+                dvec3(mid.x, mid.y, 0.0),
+                dvec3(end.x, end.y, 0.0),
+            ));
+            edges.push(Edge::segment(
+                dvec3(end.x, end.y, 0.0),
+                dvec3(next_start.x, next_start.y, 0.0),
+            ));
+        }
+        Wire::from_edges(&edges)
+    }
+}
+
+// even odd test , is the point inside the polygon
+pub fn inside(point: DVec2, polygon: &[DVec2]) -> bool {
+    let n = polygon.len();
+    let mut hit = false;
+    for i in 0..n {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % n];
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y);
+            if point.x < x {
+                hit = !hit;
+            }
+        }
+    }
+    hit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an L of three cells , open at the back right
+    const ELL: &str = "#.\n##\n";
+
+    #[test]
+    fn ascii_rows_start_at_the_back() {
+        let mask = Mask::from_ascii(ELL).unwrap();
+        assert_eq!((mask.width(), mask.height()), (2, 2));
+        assert!(mask.occupied(0, 0) && mask.occupied(1, 0) && mask.occupied(0, 1));
+        assert!(!mask.occupied(1, 1));
+        assert!(!mask.is_rect());
+    }
+
+    #[test]
+    fn ascii_marks() {
+        let mask = Mask::from_ascii("#xX1\n").unwrap();
+        assert!(mask.is_rect());
+        assert_eq!(mask.width(), 4);
+    }
+
+    #[test]
+    fn cells_take_semicolons_and_spaces() {
+        let mask = Mask::from_cells("0,0; 1,0\n0,1").unwrap();
+        assert_eq!(mask.cells().collect::<Vec<_>>(), [(0, 0), (1, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn bad_cells() {
+        assert!(Mask::from_cells("0;1").is_err());
+        assert!(Mask::from_cells("a,1").is_err());
+        assert!(Mask::from_cells("-1,0").is_err());
+        assert_eq!(Mask::from_cells("").unwrap_err(), "mask has no cells");
+    }
+
+    #[test]
+    fn load_reads_files_as_ascii() {
+        let path = std::env::temp_dir().join(format!("gridfin-mask-{}.txt", std::process::id()));
+        fs::write(&path, ELL).unwrap();
+        let mask = Mask::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mask.unwrap().cells().count(), 3);
+        assert_eq!(Mask::load("0,0;1,0").unwrap().cells().count(), 2);
+    }
+
+    #[test]
+    fn cells_must_share_edges() {
+        let corner = "#.\n.#\n";
+        assert_eq!(
+            Mask::from_ascii(corner).unwrap_err(),
+            "mask cells are not all joined together"
+        );
+        assert!(Mask::from_cells("0,0;2,0").is_err());
+    }
+
+    #[test]
+    fn holes_are_rejected() {
+        let ring = "###\n#.#\n###\n";
+        assert_eq!(
+            Mask::from_ascii(ring).unwrap_err(),
+            "mask has a hole at 1,1"
+        );
+        // a notch open to the outside is fine
+        assert!(Mask::from_ascii("###\n#.#\n").is_ok());
+    }
+
+    #[test]
+    fn corners_go_counter_clockwise() {
+        let mask = Mask::from_ascii(ELL).unwrap();
+        let corners = mask.corners();
+        assert_eq!(corners, [(0, 0), (2, 0), (2, 1), (1, 1), (1, 2), (0, 2)]);
+        let n = corners.len();
+        let area: i64 = (0..n)
+            .map(|i| {
+                let (a, b) = (corners[i], corners[(i + 1) % n]);
+                a.0 * b.1 - b.0 * a.1
+            })
+            .sum();
+        assert_eq!(area, 2 * 3);
+    }
+
+    #[test]
+    fn polygon_insets_inwards() {
+        let half = SIZE / 2.0;
+        let mask = Mask::rect(1, 1);
+        let outer = mask.polygon(0.0);
+        assert_eq!(outer[0], dvec2(-half, -half));
+        assert_eq!(outer[2], dvec2(half, half));
+        let inner = mask.polygon(1.0);
+        assert!((inner[0] - dvec2(1.0 - half, 1.0 - half)).length() < 1e-9);
+        assert!((inner[2] - dvec2(half - 1.0, half - 1.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn boxes_must_not_straddle_a_notch() {
+        // a U , open at the back middle
+        let mask = Mask::from_ascii("#.#\n###\n").unwrap();
+        let back = mask.cell_center(0, 1).y;
+        // the corners sit on the arms , the middle over the notch
+        let (min, max) = (dvec2(-SIZE, back - 5.0), dvec2(SIZE, back + 5.0));
+        assert!(mask.contains(min) && mask.contains(max));
+        assert!(!mask.holds(0.0, min, max));
+        // across the front row is fine , unless it reaches the wall
+        let front = mask.cell_center(1, 0);
+        let half = dvec2(SIZE, 5.0);
+        assert!(mask.holds(2.0, front - half, front + half));
+        assert!(!mask.holds(2.0, front - half * 1.5, front + half * 1.5));
+        // and a box off the bin is not held
+        let off = mask.cell_center(1, 1);
+        assert!(!mask.holds(0.0, off - 5.0, off + 5.0));
+    }
+
+    #[test]
+    fn contains_follows_the_cells() {
+        let mask = Mask::from_ascii(ELL).unwrap();
+        assert!(mask.contains(mask.cell_center(0, 1)));
+        assert!(!mask.contains(mask.cell_center(1, 1)));
+        assert!(!mask.contains(dvec2(-SIZE * 1.5, 0.0)));
+    }
+}
@@ -0,0 +1,125 @@
+// 2D outlines for cutouts
+// a closed polygon in mm on the xy plane , turned into wires and faces
+
+use std::fs;
+
+use glam::{dvec2, dvec3, DVec2, DVec3};
+use opencascade::primitives::{Edge, Face, Shape, Wire};
+
+#[derive(Debug, Clone)]
+pub struct Outline {
+    points: Vec<DVec2>,
+}
+
+impl Outline {
+    // always keep the points counter clockwise
+    pub fn new(points: Vec<DVec2>) -> Result<Self, String> {
+        if points.len() < 3 {
+            return Err("outline needs at least three points".to_owned());
+        }
+        let mut outline = Self { points };
+        outline.points.dedup_by(|a, b| a.distance(*b) < 1e-6);
+        if outline.points.first().unwrap().distance(*outline.points.last().unwrap()) < 1e-6 {
+            outline.points.pop();
+        }
+        if outline.area() < 0.0 {
+            outline.points.reverse();
+        }
+        Ok(outline)
+    }
+
+    // text file , one "x,y" point per line , # for comments
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut points = Vec::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (x, y) = line
+                .split_once(',')
+                .ok_or(format!("bad point {:?} , expected x,y", line))?;
+            let x: f64 = x.trim().parse().map_err(|_| format!("bad point {:?}", line))?;
+            let y: f64 = y.trim().parse().map_err(|_| format!("bad point {:?}", line))?;
+            points.push(dvec2(x, y));
+        }
+        Self::new(points)
+    }
+
+    pub fn points(&self) -> &[DVec2] {
+        &self.points
+    }
+
+    // signed area , positive when counter clockwise
+    pub fn area(&self) -> f64 {
+        let n = self.points.len();
+        (0..n)
+            .map(|i| self.points[i].perp_dot(self.points[(i + 1) % n]))
+            .sum::<f64>()
+            / 2.0
+    }
+
+    // grow ( or shrink with a negative distance ) with mitred corners
+    pub fn offset(&self, distance: f64) -> Self {
+        let n = self.points.len();
+        let points = (0..n)
+            .map(|i| {
+                let prev = self.points[(i + n - 1) % n];
+                let c = self.points[i];
+                let next = self.points[(i + 1) % n];
+                // outward normals , right of a ccw edge
+                let n1 = -(c - prev).normalize().perp();
+                let n2 = -(next - c).normalize().perp();
+                let miter = n1 + n2;
+                c + distance * miter / (1.0 + n1.dot(n2)).max(0.25)
+            })
+            .collect();
+        Self { points }
+    }
+
+    // rotate about the origin , angle in degrees
+    pub fn rotate(&self, angle: f64) -> Self {
+        let r = DVec2::from_angle(angle.to_radians());
+        Self {
+            points: self.points.iter().map(|p| r.rotate(*p)).collect(),
+        }
+    }
+
+    pub fn translate(&self, offset: DVec2) -> Self {
+        Self {
+            points: self.points.iter().map(|p| *p + offset).collect(),
+        }
+    }
+
+    pub fn wire(&self) -> Wire {
+        let n = self.points.len();
+        let edges: Vec<Edge> = (0..n)
+            .map(|i| {
+                let a = self.points[i];
+                let b = self.points[(i + 1) % n];
+                Edge::segment(dvec3(a.x, a.y, 0.0), dvec3(b.x, b.y, 0.0))
+            })
+            .collect();
+        Wire::from_edges(&edges)
+    }
+
+    pub fn face(&self) -> Face {
+        self.wire().to_face()
+    }
+
+    // put the outline on a plane , x along `u` and y along `v`
+    // from `origin` , and push it out along `through`
+    pub fn prism(&self, origin: DVec3, u: DVec3, v: DVec3, through: DVec3) -> Shape {
+        let points: Vec<DVec3> = self
+            .points
+            .iter()
+            .map(|p| origin + p.x * u + p.y * v)
+            .collect();
+        let n = points.len();
+        let edges: Vec<Edge> = (0..n)
+            .map(|i| Edge::segment(points[i], points[(i + 1) % n]))
+            .collect();
+        Wire::from_edges(&edges).to_face().extrude(through).into()
+    }
+}
@@ -0,0 +1,452 @@
+// Part settings
+// one bin , base plate or lid as json , for the server and for batch
+// manifests , with the same names and defaults as the command line
+// shadows , holders , labels and codes are only on the command line
+
+use serde::{Deserialize, Serialize};
+
+use crate::base::{BaseFloor, Bottom, Cleat, Hanger, Keyholes, Mount, Placement, Screws};
+use crate::cache;
+use crate::divider::{positions, Axis, Divider};
+use crate::export::{Body, Format};
+use crate::gridfin::{
+    lid, lite, open, units_for, BasePlate, BinOptions, Walls, CAVITY_FILLET, FILLET, WALL_THICKNESS,
+};
+use crate::mask::Mask;
+use crate::pattern::{Pattern, Style};
+use crate::tier::{Floor, Tiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Bin,
+    Baseplate,
+    Lid,
+}
+
+// the settings of one part , named as on the command line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Params {
+    // file name for batches , not part of the key
+    #[serde(skip_serializing)]
+    pub name: Option<String>,
+    pub kind: Kind,
+    pub width: usize,
+    pub length: usize,
+    pub height: usize,
+    // overall height in mm , overrides height with the most units that fit
+    pub height_mm: Option<f64>,
+    // height_mm stops at the stacking line
+    pub height_excludes_lip: bool,
+    // a cell list or ascii grid , overrides width and length
+    pub mask: Option<String>,
+    pub format: Format,
+    pub wall: f64,
+    pub floor: f64,
+    pub fillet: f64,
+    pub cavity_fillet: f64,
+    pub lite: bool,
+    pub shell: f64,
+    pub slot_x: Vec<f64>,
+    pub slot_y: Vec<f64>,
+    pub slot_depth: Option<f64>,
+    pub divider_thickness: f64,
+    pub pattern: Option<Style>,
+    pub cell: f64,
+    pub strut: f64,
+    pub tiers: Option<Floor>,
+    pub tier_count: usize,
+    pub tier_rise: f64,
+    // base plate floor and screws
+    pub bottom: Bottom,
+    pub bottom_thickness: f64,
+    pub screws: Option<Placement>,
+    pub screw_diameter: f64,
+    pub screw_head: f64,
+    // hanging the base plate on a wall
+    pub mount: Option<Hanger>,
+    pub cleat_angle: f64,
+    pub cleat_thickness: f64,
+    pub cleat_height: f64,
+    pub keyhole_spacing: Option<f64>,
+    pub keyhole_head: f64,
+    pub keyhole_shank: f64,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            name: None,
+            kind: Kind::Bin,
+            width: 1,
+            length: 1,
+            height: 1,
+            height_mm: None,
+            height_excludes_lip: false,
+            mask: None,
+            format: Format::Stl,
+            wall: WALL_THICKNESS,
+            floor: 0.0,
+            fillet: FILLET,
+            cavity_fillet: CAVITY_FILLET,
+            lite: false,
+            shell: 0.8,
+            slot_x: Vec::new(),
+            slot_y: Vec::new(),
+            slot_depth: None,
+            divider_thickness: 1.2,
+            pattern: None,
+            cell: 8.0,
+            strut: 2.0,
+            tiers: None,
+            tier_count: 3,
+            tier_rise: 7.0,
+            bottom: Bottom::Open,
+            bottom_thickness: 4.0,
+            screws: None,
+            screw_diameter: 3.4,
+            screw_head: 6.4,
+            mount: None,
+            cleat_angle: 45.0,
+            cleat_thickness: 6.0,
+            cleat_height: 20.0,
+            keyhole_spacing: None,
+            keyhole_head: 8.0,
+            keyhole_shank: 4.0,
+        }
+    }
+}
+
+// the settings each kind takes on top of the footprint and format
+const BIN: &[&str] = &[
+    "height",
+    "height_mm",
+    "height_excludes_lip",
+    "wall",
+    "floor",
+    "fillet",
+    "cavity_fillet",
+    "lite",
+    "shell",
+    "slot_x",
+    "slot_y",
+    "slot_depth",
+    "divider_thickness",
+    "pattern",
+    "cell",
+    "strut",
+    "tiers",
+    "tier_count",
+    "tier_rise",
+];
+const BASEPLATE: &[&str] = &[
+    "bottom",
+    "bottom_thickness",
+    "screws",
+    "screw_diameter",
+    "screw_head",
+    "mount",
+    "cleat_angle",
+    "cleat_thickness",
+    "cleat_height",
+    "keyhole_spacing",
+    "keyhole_head",
+    "keyhole_shank",
+];
+
+impl Params {
+    // settings changed from their defaults that the kind doesn't take
+    pub fn unused(&self) -> Result<(), String> {
+        let (kind, takes) = match self.kind {
+            Kind::Bin => ("bin", BIN),
+            Kind::Baseplate => ("base plate", BASEPLATE),
+            Kind::Lid => ("lid", &[] as &[&str]),
+        };
+        let value = serde_json::to_value(self).unwrap();
+        let default = serde_json::to_value(Params::default()).unwrap();
+        for (name, v) in value.as_object().unwrap() {
+            let specific = BIN.contains(&name.as_str()) || BASEPLATE.contains(&name.as_str());
+            if specific && !takes.contains(&name.as_str()) && *v != default[name] {
+                return Err(format!("{} is not a {} setting", name, kind));
+            }
+        }
+        Ok(())
+    }
+
+    fn mask(&self) -> Result<Mask, String> {
+        match &self.mask {
+            Some(spec) => {
+                // inline text only , no files from the network
+                if spec.contains(',') {
+                    Mask::from_cells(spec)
+                } else {
+                    Mask::from_ascii(spec)
+                }
+            }
+            None if self.width == 0 || self.length == 0 => {
+                Err("width and length must be at least 1".to_owned())
+            }
+            None => Ok(Mask::rect(self.width, self.length)),
+        }
+    }
+
+    // a stable name for the settings and the version that made them
+    pub fn key(&self) -> String {
+        cache::key("part", &serde_json::to_string(self).unwrap())
+    }
+
+    // bin height in units
+    fn units(&self) -> Result<usize, String> {
+        match self.height_mm {
+            Some(mm) => units_for(mm, !self.height_excludes_lip),
+            None if self.height == 0 => Err("height must be at least 1".to_owned()),
+            None => Ok(self.height),
+        }
+    }
+
+    // the bin extras , checked
+    fn options(&self) -> Result<BinOptions, String> {
+        let mut options = BinOptions::default();
+        if self.kind != Kind::Bin {
+            return Ok(options);
+        }
+        let height = self.units()?;
+        let mask = self.mask()?;
+        let walls = Walls {
+            thickness: self.wall,
+            floor: self.floor,
+            fillet: self.fillet,
+            cavity_fillet: self.cavity_fillet,
+        };
+        if self.lite {
+            if walls != Walls::default() {
+                return Err("lite bins have their own shell , no wall sizes".to_owned());
+            }
+            if !self.slot_x.is_empty()
+                || !self.slot_y.is_empty()
+                || self.pattern.is_some()
+                || self.tiers.is_some()
+            {
+                return Err("lite bins are plain shells , no slots or patterns".to_owned());
+            }
+            if self.shell <= 0.0 {
+                return Err("shell thickness must be more than zero".to_owned());
+            }
+            return Ok(options);
+        }
+        walls.check(height)?;
+        options.walls = walls;
+        let mut slots = positions(Axis::X, &self.slot_x);
+        slots.extend(positions(Axis::Y, &self.slot_y));
+        if !slots.is_empty() {
+            let mut d = Divider::new(slots);
+            d.thickness = self.divider_thickness;
+            d.slot_depth = self
+                .slot_depth
+                .unwrap_or_else(|| Divider::slot_depth_for(&walls));
+            d.check(&mask, &walls)?;
+            options.divider = Some(d);
+        }
+        if let Some(style) = self.pattern {
+            // the holes are laid out on plain walls
+            if options.divider.is_some() || self.tiers.is_some() {
+                return Err("patterned walls can't have divider slots or tiers".to_owned());
+            }
+            let mut p = Pattern::new(style);
+            p.cell = self.cell;
+            p.strut = self.strut;
+            p.check()?;
+            options.pattern = Some(p);
+        }
+        if let Some(floor) = self.tiers {
+            // divider plates are cut for a flat floor
+            if options.divider.is_some() {
+                return Err("tiered bins can't have divider slots".to_owned());
+            }
+            let t = Tiers::new(floor, self.tier_count, self.tier_rise);
+            t.check(&walls, height)?;
+            options.tiers = Some(t);
+        }
+        Ok(options)
+    }
+
+    // the base plate floor , checked
+    fn floor(&self, mask: &Mask) -> Result<BaseFloor, String> {
+        let mut floor = BaseFloor::new(self.bottom);
+        floor.thickness = self.bottom_thickness;
+        if let Some(placement) = self.screws {
+            let mut screws = Screws::new(placement);
+            screws.diameter = self.screw_diameter;
+            screws.head = self.screw_head;
+            floor.screws = Some(screws);
+        }
+        let mount = match self.mount {
+            Some(Hanger::Cleat) => Some(Mount::Cleat(Cleat {
+                angle: self.cleat_angle,
+                thickness: self.cleat_thickness,
+                height: self.cleat_height,
+            })),
+            Some(Hanger::Keyhole) => Some(Mount::Keyholes(Keyholes {
+                spacing: self.keyhole_spacing,
+                head: self.keyhole_head,
+                shank: self.keyhole_shank,
+                ..Default::default()
+            })),
+            None => None,
+        };
+        if let Some(mount) = mount {
+            floor.set_mount(mount);
+        }
+        floor.check(mask)?;
+        Ok(floor)
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        self.unused()?;
+        let mask = self.mask()?;
+        match self.kind {
+            Kind::Bin => self.options().map(|_| ()),
+            Kind::Baseplate => self.floor(&mask).map(|_| ()),
+            Kind::Lid => Ok(()),
+        }
+    }
+
+    pub fn bodies(&self) -> Result<Vec<Body>, String> {
+        self.unused()?;
+        let mask = self.mask()?;
+        let options = self.options()?;
+        let shape = match self.kind {
+            Kind::Baseplate => {
+                let mut plate = BasePlate::from_mask(&mask);
+                plate.floor = self.floor(&mask)?;
+                plate.shape()
+            }
+            Kind::Lid => lid(&mask),
+            Kind::Bin if self.lite => lite(&mask, self.units()?, self.shell),
+            Kind::Bin => open(&mask, self.units()?, &options),
+        };
+        Ok(vec![Body::new("gridfin", shape)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<Params, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn missing_settings_are_the_defaults() {
+        let params = parse("{}").unwrap();
+        assert_eq!(params.key(), Params::default().key());
+        assert_eq!(params.kind, Kind::Bin);
+        assert_eq!((params.width, params.length, params.height), (1, 1, 1));
+        assert_eq!(params.format, Format::Stl);
+    }
+
+    #[test]
+    fn wall_defaults_match_open_bins() {
+        let params = Params::default();
+        let walls = Walls::default();
+        assert_eq!(params.wall, walls.thickness);
+        assert_eq!(params.floor, walls.floor);
+        assert_eq!(params.fillet, walls.fillet);
+        assert_eq!(params.cavity_fillet, walls.cavity_fillet);
+    }
+
+    #[test]
+    fn names_are_lowercase() {
+        let params = parse(r#"{"kind": "baseplate", "format": "3mf", "width": 2}"#).unwrap();
+        assert_eq!(params.kind, Kind::Baseplate);
+        assert_eq!(params.format, Format::ThreeMf);
+        assert_eq!(params.width, 2);
+        assert_eq!(params.length, 1);
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        let err = parse(r#"{"widht": 2}"#).unwrap_err();
+        assert!(err.contains("unknown field `widht`"), "{}", err);
+        assert!(parse(r#"{"kind": "box"}"#).is_err());
+    }
+
+    #[test]
+    fn the_name_is_not_in_the_key() {
+        let a = parse(r#"{"name": "a", "width": 2}"#).unwrap();
+        let b = parse(r#"{"name": "b", "width": 2}"#).unwrap();
+        assert_eq!(a.key(), b.key());
+        assert_ne!(a.key(), Params::default().key());
+    }
+
+    #[test]
+    fn masks_and_heights() {
+        let mut params = Params {
+            mask: Some("0,0;1,0".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(params.mask().unwrap().width(), 2);
+        params.mask = Some("##\n#.\n".to_owned());
+        assert_eq!(params.mask().unwrap().cells().count(), 3);
+        params.mask = None;
+        params.width = 0;
+        assert!(params.mask().is_err());
+        params.height_mm = Some(32.75);
+        assert_eq!(params.units(), Ok(3));
+        params.height_excludes_lip = true;
+        params.height_mm = Some(28.0);
+        assert_eq!(params.units(), Ok(3));
+    }
+
+    #[test]
+    fn settings_must_suit_the_kind() {
+        let err = parse(r#"{"kind": "baseplate", "slot_x": [0]}"#)
+            .unwrap()
+            .check()
+            .unwrap_err();
+        assert_eq!(err, "slot_x is not a base plate setting");
+        let err = parse(r#"{"kind": "lid", "bottom": "solid"}"#)
+            .unwrap()
+            .check()
+            .unwrap_err();
+        assert_eq!(err, "bottom is not a lid setting");
+        let err = parse(r#"{"lite": true, "mount": "cleat"}"#)
+            .unwrap()
+            .check()
+            .unwrap_err();
+        assert_eq!(err, "mount is not a bin setting");
+        // the defaults are fine , and so is the footprint
+        let lid = parse(r#"{"kind": "lid", "width": 2, "wall": 2.15, "format": "3mf"}"#);
+        assert!(lid.unwrap().check().is_ok());
+    }
+
+    #[test]
+    fn patterns_need_plain_walls() {
+        let mut params = parse(r#"{"width": 2, "height": 6, "pattern": "hex"}"#).unwrap();
+        assert!(params.options().is_ok());
+        params.slot_x = vec![0.0];
+        let err = params.options().unwrap_err();
+        assert!(err.starts_with("patterned walls"), "{}", err);
+        params.slot_x.clear();
+        params.tiers = Some(Floor::Steps);
+        let err = params.options().unwrap_err();
+        assert!(err.starts_with("patterned walls"), "{}", err);
+    }
+
+    #[test]
+    fn default_mounts_work() {
+        for mount in ["cleat", "keyhole"] {
+            let json = format!(
+                r#"{{"kind": "baseplate", "width": 3, "mount": "{}"}}"#,
+                mount
+            );
+            let params = parse(&json).unwrap();
+            assert!(params.check().is_ok(), "{}", mount);
+        }
+        let params = parse(r#"{"kind": "baseplate", "width": 3, "mount": "keyhole"}"#).unwrap();
+        let floor = params.floor(&params.mask().unwrap()).unwrap();
+        assert_eq!(floor.bottom, Bottom::Solid);
+    }
+}
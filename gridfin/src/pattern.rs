@@ -0,0 +1,146 @@
+// Patterned walls
+// hex or slot holes cut through the outer walls to save filament
+// and to see into the bin , solid bands are left at the base , under
+// the lip and around the corners so the bin stays stiff
+
+use clap::ValueEnum;
+use glam::{dvec2, dvec3, DVec2, DVec3};
+use opencascade::primitives::Shape;
+use serde::{Deserialize, Serialize};
+
+use crate::gridfin::{Walls, V_UNIT};
+use crate::mask::Mask;
+use crate::outline::Outline;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    // honeycomb of pointy topped hexagons
+    Hex,
+    // upright slots
+    Slot,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub style: Style,
+    // across the flats of a hex , or the width of a slot
+    pub cell: f64,
+    // material left between the holes
+    pub strut: f64,
+}
+
+impl Pattern {
+    // solid band above the floor
+    const BASE_MARGIN: f64 = 3.0;
+    // solid band under the lip
+    const LIP_MARGIN: f64 = 3.0;
+    // solid band each side of a corner , past the fillet
+    const CORNER_MARGIN: f64 = 3.0;
+
+    pub fn new(style: Style) -> Self {
+        Self {
+            style,
+            cell: 8.0,
+            strut: 2.0,
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.cell <= 0.0 || self.strut <= 0.0 {
+            return Err("pattern cell and strut must be more than zero".to_owned());
+        }
+        Ok(())
+    }
+
+    // holes on a wall face , (along , up) centers and their outline
+    fn holes(&self, length: f64, height: f64) -> Vec<Vec<DVec2>> {
+        let pitch = self.cell + self.strut;
+        let mut holes = Vec::new();
+        match self.style {
+            Style::Hex => {
+                // corner to corner is taller than across the flats
+                let radius = self.cell / 3f64.sqrt();
+                let row_pitch = pitch * 3f64.sqrt() / 2.0;
+                let rows = ((height - 2.0 * radius) / row_pitch).floor() as i64 + 1;
+                let cols = ((length - self.cell) / pitch).floor() as i64 + 1;
+                if rows < 1 || cols < 1 || height < 2.0 * radius {
+                    return holes;
+                }
+                let v0 = (height - row_pitch * (rows - 1) as f64) / 2.0;
+                for row in 0..rows {
+                    // staggered rows lose one
+                    let count = if row % 2 == 1 { cols - 1 } else { cols };
+                    let u0 = (length - pitch * (count - 1) as f64) / 2.0;
+                    for col in 0..count {
+                        let center = dvec2(u0 + pitch * col as f64, v0 + row_pitch * row as f64);
+                        holes.push(
+                            (0..6)
+                                .map(|i| {
+                                    let a = std::f64::consts::TAU * i as f64 / 6.0
+                                        + std::f64::consts::FRAC_PI_2;
+                                    center + radius * dvec2(a.cos(), a.sin())
+                                })
+                                .collect(),
+                        );
+                    }
+                }
+            }
+            Style::Slot => {
+                let cols = ((length - self.cell) / pitch).floor() as i64 + 1;
+                if cols < 1 || height <= self.cell {
+                    return holes;
+                }
+                let u0 = (length - pitch * (cols - 1) as f64) / 2.0;
+                let half = self.cell / 2.0;
+                for col in 0..cols {
+                    let u = u0 + pitch * col as f64;
+                    holes.push(vec![
+                        dvec2(u - half, 0.0),
+                        dvec2(u + half, 0.0),
+                        dvec2(u + half, height),
+                        dvec2(u - half, height),
+                    ]);
+                }
+            }
+        }
+        holes
+    }
+
+    // all the holes for the walls of a bin of `height` units
+    pub fn cutter(&self, mask: &Mask, walls: &Walls, height: usize) -> Option<Shape> {
+        let bottom = walls.bottom() + Pattern::BASE_MARGIN;
+        let top = V_UNIT * (height + 1) as f64 - Pattern::LIP_MARGIN;
+        if top <= bottom {
+            return None;
+        }
+        let corners = mask.polygon(0.0);
+        let n = corners.len();
+        let mut cutter: Option<Shape> = None;
+        for i in 0..n {
+            let a = corners[i];
+            let b = corners[(i + 1) % n];
+            let dir = (b - a).normalize();
+            // outward normal , right of a ccw edge
+            let out = -dir.perp();
+            let margin = walls.fillet + Pattern::CORNER_MARGIN;
+            let length = a.distance(b) - 2.0 * margin;
+            if length <= 0.0 {
+                continue;
+            }
+            // the hole plane sits just outside the wall
+            let start = a + margin * dir + out;
+            let origin = dvec3(start.x, start.y, bottom);
+            let along = dvec3(dir.x, dir.y, 0.0);
+            let through = -(walls.thickness + 2.0) * dvec3(out.x, out.y, 0.0);
+            for hole in self.holes(length, top - bottom) {
+                let prism = Outline::new(hole).unwrap().prism(origin, along, DVec3::Z, through);
+                cutter = Some(match cutter {
+                    Some(c) => c.union(&prism).into(),
+                    None => prism,
+                });
+            }
+        }
+        cutter
+    }
+}
@@ -0,0 +1,205 @@
+// Preview images
+// the shapes are meshed and drawn from an isometric view with a
+// small software rasteriser , so no gpu or display is needed
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use glam::{dvec3, DVec3};
+
+use crate::export::Body;
+
+const BACKGROUND: [u8; 3] = [0xff, 0xff, 0xff];
+// light comes over the left shoulder of the viewer
+const LIGHT: DVec3 = DVec3::new(-0.4, 0.3, 0.85);
+const AMBIENT: f64 = 0.35;
+// border around the part as a fraction of the image
+const MARGIN: f64 = 0.05;
+
+// turn about z by 45 degrees then tip over so z points up the
+// screen , x right , y up and depth toward the viewer
+fn isometric(p: DVec3) -> DVec3 {
+    let (s, c) = std::f64::consts::FRAC_PI_4.sin_cos();
+    let x = c * p.x - s * p.y;
+    let y = s * p.x + c * p.y;
+    // atan(1 / sqrt(2)) , the true isometric tilt
+    let tilt = (1.0 / 2f64.sqrt()).atan();
+    let (st, ct) = tilt.sin_cos();
+    dvec3(x, ct * p.z + st * y, st * p.z - ct * y)
+}
+
+struct Triangle {
+    points: [DVec3; 3],
+    color: [u8; 3],
+}
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn write_png(&self, path: &str) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("{}: {}", path, e);
+        let file = File::create(path).map_err(|e| err(&e))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| err(&e))?;
+        writer.write_image_data(&self.pixels).map_err(|e| err(&e))
+    }
+}
+
+pub fn render(bodies: &[Body], width: usize, height: usize) -> Image {
+    let mut triangles = Vec::new();
+    for body in bodies {
+        let color = body.color.unwrap_or([0x80, 0x80, 0x80]);
+        let mesh = body.shape.mesh();
+        for t in mesh.indices.chunks(3) {
+            triangles.push(Triangle {
+                points: [
+                    isometric(mesh.vertices[t[0]]),
+                    isometric(mesh.vertices[t[1]]),
+                    isometric(mesh.vertices[t[2]]),
+                ],
+                color,
+            });
+        }
+    }
+    draw(&triangles, width, height)
+}
+
+// the triangles shaded and scaled to fill the image , nearest on top
+fn draw(triangles: &[Triangle], width: usize, height: usize) -> Image {
+    let mut image = Image {
+        width,
+        height,
+        pixels: BACKGROUND.repeat(width * height),
+    };
+    if triangles.is_empty() {
+        return image;
+    }
+    // fit the part in the frame
+    let mut min = DVec3::splat(f64::MAX);
+    let mut max = DVec3::splat(f64::MIN);
+    for p in triangles.iter().flat_map(|t| t.points.iter()) {
+        min = min.min(*p);
+        max = max.max(*p);
+    }
+    let usable = (1.0 - 2.0 * MARGIN) * width.min(height) as f64;
+    let scale = usable / (max.x - min.x).max(max.y - min.y).max(1e-9);
+    let center = (min + max) / 2.0;
+    let to_screen = |p: DVec3| {
+        dvec3(
+            width as f64 / 2.0 + (p.x - center.x) * scale,
+            height as f64 / 2.0 - (p.y - center.y) * scale,
+            p.z,
+        )
+    };
+    let light = LIGHT.normalize();
+    let mut depth = vec![f64::MIN; width * height];
+    for t in triangles {
+        let normal = (t.points[1] - t.points[0]).cross(t.points[2] - t.points[0]);
+        if normal.length_squared() == 0.0 {
+            continue;
+        }
+        // lit from either side , the mesh winding is not trusted
+        let shade = AMBIENT + (1.0 - AMBIENT) * normal.normalize().dot(light).abs();
+        let color = t.color.map(|c| (c as f64 * shade).min(255.0) as u8);
+        let [a, b, c] = t.points.map(&to_screen);
+        let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        if area.abs() < 1e-12 {
+            continue;
+        }
+        let x0 = a.x.min(b.x).min(c.x).floor().max(0.0) as usize;
+        let x1 = (a.x.max(b.x).max(c.x).ceil() as usize).min(width - 1);
+        let y0 = a.y.min(b.y).min(c.y).floor().max(0.0) as usize;
+        let y1 = (a.y.max(b.y).max(c.y).ceil() as usize).min(height - 1);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                // barycentric weights from the edge functions
+                let w0 = ((b.x - px) * (c.y - py) - (b.y - py) * (c.x - px)) / area;
+                let w1 = ((c.x - px) * (a.y - py) - (c.y - py) * (a.x - px)) / area;
+                let w2 = 1.0 - w0 - w1;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+                let z = w0 * a.z + w1 * b.z + w2 * c.z;
+                let i = y * width + x;
+                if z > depth[i] {
+                    depth[i] = z;
+                    image.pixels[i * 3..i * 3 + 3].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: DVec3, b: DVec3) -> bool {
+        (a - b).length() < 1e-9
+    }
+
+    #[test]
+    fn isometric_axes_look_the_same_length() {
+        // z stays upright
+        let z = isometric(DVec3::Z);
+        assert!(z.x.abs() < 1e-9 && z.y > 0.0);
+        let on_screen = |p: DVec3| p.truncate().length();
+        let x = on_screen(isometric(DVec3::X));
+        assert!((x - on_screen(isometric(DVec3::Y))).abs() < 1e-9);
+        assert!((x - on_screen(z)).abs() < 1e-9);
+        assert!((x - (2.0f64 / 3.0).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn the_viewer_looks_down_the_diagonal() {
+        let eye = dvec3(-1.0, -1.0, 1.0);
+        assert!(close(isometric(eye), dvec3(0.0, 0.0, 3f64.sqrt())));
+        // a turn , nothing is stretched
+        let p = dvec3(3.0, -2.0, 5.0);
+        assert!((isometric(p).length() - p.length()).abs() < 1e-9);
+    }
+
+    fn triangle(z: f64, color: [u8; 3]) -> Triangle {
+        Triangle {
+            points: [dvec3(0.0, 0.0, z), dvec3(10.0, 0.0, z), dvec3(0.0, 10.0, z)],
+            color,
+        }
+    }
+
+    fn pixel(image: &Image, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * image.width + x) * 3;
+        [image.pixels[i], image.pixels[i + 1], image.pixels[i + 2]]
+    }
+
+    #[test]
+    fn triangles_fill_the_frame() {
+        let image = draw(&[triangle(0.0, [200, 0, 0])], 100, 100);
+        // the right angle is at the bottom left , inside the margin
+        assert_eq!(pixel(&image, 2, 97), BACKGROUND);
+        assert_ne!(pixel(&image, 8, 91), BACKGROUND);
+        assert_eq!(pixel(&image, 90, 10), BACKGROUND);
+        assert_eq!(draw(&[], 4, 4).pixels, BACKGROUND.repeat(16));
+    }
+
+    #[test]
+    fn nearer_triangles_cover_further_ones() {
+        let near = [200, 0, 0];
+        let far = [0, 0, 200];
+        for list in [
+            [triangle(1.0, near), triangle(0.0, far)],
+            [triangle(0.0, far), triangle(1.0, near)],
+        ] {
+            let image = draw(&list, 100, 100);
+            let [r, _, b] = pixel(&image, 20, 80);
+            assert!(r > 0 && b == 0);
+        }
+    }
+}
@@ -0,0 +1,641 @@
+// 2D profile import
+// closed paths from svg or dxf drawings become outlines ,
+// curves and arcs are flattened into short straight runs
+
+use std::fs;
+use std::path::Path;
+
+use glam::{dvec2, DVec2};
+use svgtypes::{SimplePathSegment, SimplifyingPathParser, Transform};
+
+use crate::outline::Outline;
+
+// biggest gap between a curve and its flattened line
+const TOLERANCE: f64 = 0.05;
+// how close path ends have to be to count as joined
+const JOIN: f64 = 0.01;
+// most times a curve piece is halved
+const MAX_SPLITS: usize = 16;
+
+// pick the importer from the file extension
+pub fn load(path: &str) -> Result<Vec<Outline>, String> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let outlines = match ext.as_str() {
+        "svg" => load_svg(path)?,
+        "dxf" => load_dxf(path)?,
+        _ => vec![Outline::load(path)?],
+    };
+    if outlines.is_empty() {
+        return Err(format!("{}: no closed paths", path));
+    }
+    Ok(outlines)
+}
+
+// number of pieces to cut a curve into
+fn steps(length: f64, radius: f64) -> usize {
+    if radius <= TOLERANCE {
+        return 1;
+    }
+    let angle = 2.0 * (1.0 - TOLERANCE / radius).acos();
+    ((length / radius / angle).ceil() as usize).clamp(1, 256)
+}
+
+// distance from `p` to the segment a b
+fn gap(p: DVec2, a: DVec2, b: DVec2) -> f64 {
+    let ab = b - a;
+    let t = if ab.length_squared() < 1e-18 {
+        0.0
+    } else {
+        ((p - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+    };
+    p.distance(a + t * ab)
+}
+
+// halved until the control points are within the tolerance of the
+// chord , the curve stays inside them so it is too
+pub(crate) fn cubic(p0: DVec2, p1: DVec2, p2: DVec2, p3: DVec2, out: &mut Vec<DVec2>) {
+    flatten(p0, p1, p2, p3, 0, out);
+}
+
+fn flatten(p0: DVec2, p1: DVec2, p2: DVec2, p3: DVec2, splits: usize, out: &mut Vec<DVec2>) {
+    if splits >= MAX_SPLITS || gap(p1, p0, p3).max(gap(p2, p0, p3)) <= TOLERANCE {
+        out.push(p3);
+        return;
+    }
+    // de casteljau at the middle
+    let a = (p0 + p1) / 2.0;
+    let b = (p1 + p2) / 2.0;
+    let c = (p2 + p3) / 2.0;
+    let d = (a + b) / 2.0;
+    let e = (b + c) / 2.0;
+    let m = (d + e) / 2.0;
+    flatten(p0, a, d, m, splits + 1, out);
+    flatten(m, e, c, p3, splits + 1, out);
+}
+
+// a point on a b-spline by de boor's algorithm , `span` is the
+// knot interval holding t
+fn de_boor(degree: usize, knots: &[f64], points: &[DVec2], span: usize, t: f64) -> DVec2 {
+    let mut d: Vec<DVec2> = (0..=degree).map(|j| points[j + span - degree]).collect();
+    for r in 1..=degree {
+        for j in (r..=degree).rev() {
+            let i = j + span - degree;
+            let width = knots[i + degree + 1 - r] - knots[i];
+            let alpha = if width.abs() < 1e-12 {
+                0.0
+            } else {
+                (t - knots[i]) / width
+            };
+            d[j] = (1.0 - alpha) * d[j - 1] + alpha * d[j];
+        }
+    }
+    d[degree]
+}
+
+// a b-spline with its knot vector , each knot span halved until the
+// middle is within the tolerance , and at least into quarters so
+// a bend is not missed
+fn spline(degree: usize, knots: &[f64], points: &[DVec2], out: &mut Vec<DVec2>) {
+    let at = |span: usize, t: f64| de_boor(degree, knots, points, span, t);
+    for span in degree..points.len() {
+        let (t0, t1) = (knots[span], knots[span + 1]);
+        if t1 - t0 < 1e-12 {
+            continue;
+        }
+        if out.is_empty() {
+            out.push(at(span, t0));
+        }
+        let mut pieces = vec![(t0, t1, 0)];
+        while let Some((a, b, splits)) = pieces.pop() {
+            let (pa, pb) = (at(span, a), at(span, b));
+            let m = (a + b) / 2.0;
+            let flat = splits >= 2 && gap(at(span, m), pa, pb) <= TOLERANCE;
+            if flat || splits >= MAX_SPLITS {
+                out.push(pb);
+            } else {
+                // the far half goes on first so the near one comes off next
+                pieces.push((m, b, splits + 1));
+                pieces.push((a, m, splits + 1));
+            }
+        }
+    }
+}
+
+// arc from start to end angle ( radians , counter clockwise )
+fn arc(center: DVec2, radius: f64, start: f64, end: f64, out: &mut Vec<DVec2>) {
+    let mut sweep = end - start;
+    if sweep <= 0.0 {
+        sweep += std::f64::consts::TAU;
+    }
+    let n = steps(radius * sweep, radius);
+    for i in 1..=n {
+        let a = start + sweep * i as f64 / n as f64;
+        out.push(center + radius * dvec2(a.cos(), a.sin()));
+    }
+}
+
+// join open runs end to end until they close
+fn chain(mut runs: Vec<Vec<DVec2>>, closed: &mut Vec<Vec<DVec2>>) {
+    while let Some(mut run) = runs.pop() {
+        loop {
+            if run.len() > 2 && run[0].distance(*run.last().unwrap()) < JOIN {
+                closed.push(run);
+                break;
+            }
+            let end = *run.last().unwrap();
+            let found = runs.iter().position(|r| {
+                r[0].distance(end) < JOIN || r.last().unwrap().distance(end) < JOIN
+            });
+            match found {
+                Some(i) => {
+                    let mut next = runs.swap_remove(i);
+                    if next[0].distance(end) >= JOIN {
+                        next.reverse();
+                    }
+                    run.extend(next.into_iter().skip(1));
+                }
+                // left open , not a profile
+                None => break,
+            }
+        }
+    }
+}
+
+fn outlines(closed: Vec<Vec<DVec2>>) -> Vec<Outline> {
+    closed.into_iter().filter_map(|p| Outline::new(p).ok()).collect()
+}
+
+// svg
+
+fn length_mm(value: Option<&str>) -> Option<f64> {
+    let value = value?.trim();
+    let (number, unit) = value.split_at(
+        value
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+            .unwrap_or(value.len()),
+    );
+    let number: f64 = number.parse().ok()?;
+    let scale = match unit {
+        "mm" => 1.0,
+        "cm" => 10.0,
+        "in" => 25.4,
+        "pt" => 25.4 / 72.0,
+        // css pixels
+        "" | "px" => 25.4 / 96.0,
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+fn apply(t: &Transform, p: DVec2) -> DVec2 {
+    dvec2(t.a * p.x + t.c * p.y + t.e, t.b * p.x + t.d * p.y + t.f)
+}
+
+fn multiply(a: &Transform, b: &Transform) -> Transform {
+    Transform::new(
+        a.a * b.a + a.c * b.b,
+        a.b * b.a + a.d * b.b,
+        a.a * b.c + a.c * b.d,
+        a.b * b.c + a.d * b.d,
+        a.a * b.e + a.c * b.f + a.e,
+        a.b * b.e + a.d * b.f + a.f,
+    )
+}
+
+// the path data for the shapes we understand
+fn path_data(node: &roxmltree::Node) -> Option<String> {
+    let num = |name: &str| node.attribute(name).and_then(|v| v.parse::<f64>().ok()).unwrap_or(0.0);
+    match node.tag_name().name() {
+        "path" => node.attribute("d").map(|d| d.to_owned()),
+        "polygon" => node.attribute("points").map(|p| format!("M {} Z", p)),
+        "rect" => {
+            let (x, y, w, h) = (num("x"), num("y"), num("width"), num("height"));
+            Some(format!("M {} {} h {} v {} h {} Z", x, y, w, h, -w))
+        }
+        "circle" | "ellipse" => {
+            let (cx, cy) = (num("cx"), num("cy"));
+            let (rx, ry) = if node.tag_name().name() == "circle" {
+                (num("r"), num("r"))
+            } else {
+                (num("rx"), num("ry"))
+            };
+            Some(format!(
+                "M {} {} A {} {} 0 1 0 {} {} A {} {} 0 1 0 {} {} Z",
+                cx + rx, cy, rx, ry, cx - rx, cy, rx, ry, cx + rx, cy
+            ))
+        }
+        _ => None,
+    }
+}
+
+// user units to mm , y up , from the page size and the view box .
+// the view box is fitted to the page as preserveAspectRatio says ,
+// stretched when it is none
+fn page(root: &roxmltree::Node) -> Transform {
+    let px = 25.4 / 96.0;
+    let view_box: Vec<f64> = root
+        .attribute("viewBox")
+        .unwrap_or("")
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    let (vx, vy, vw, vh) = match view_box[..] {
+        [x, y, w, h] if w > 0.0 && h > 0.0 => (x, y, w, h),
+        _ => return Transform::new(px, 0.0, 0.0, -px, 0.0, 0.0),
+    };
+    let width = length_mm(root.attribute("width"));
+    let height = length_mm(root.attribute("height"));
+    let (pw, ph) = match (width, height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, w * vh / vw),
+        (None, Some(h)) => (h * vw / vh, h),
+        (None, None) => (vw * px, vh * px),
+    };
+    let (mut sx, mut sy) = (pw / vw, ph / vh);
+    let (mut dx, mut dy) = (0.0, 0.0);
+    let aspect = root.attribute("preserveAspectRatio").unwrap_or("xMidYMid");
+    let mut words = aspect.split_whitespace();
+    let align = words.next().unwrap_or("xMidYMid");
+    if align != "none" {
+        let s = if words.next() == Some("slice") {
+            sx.max(sy)
+        } else {
+            sx.min(sy)
+        };
+        // how much of the spare room goes before the view box
+        let share = |key: &str| {
+            if align.contains(&format!("{}Min", key)) {
+                0.0
+            } else if align.contains(&format!("{}Max", key)) {
+                1.0
+            } else {
+                0.5
+            }
+        };
+        dx = (pw - vw * s) * share("x");
+        dy = (ph - vh * s) * share("Y");
+        sx = s;
+        sy = s;
+    }
+    // svg is y down
+    Transform::new(sx, 0.0, 0.0, -sy, dx - sx * vx, sy * vy - dy)
+}
+
+pub fn load_svg(path: &str) -> Result<Vec<Outline>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let doc = roxmltree::Document::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+    let root = doc.root_element();
+    let page = page(&root);
+    let mut closed = Vec::new();
+    let mut runs = Vec::new();
+    for node in root.descendants().filter(|n| n.is_element()) {
+        let Some(data) = path_data(&node) else {
+            continue;
+        };
+        // stack the transforms from the root down , svg is y down
+        let mut t = page;
+        let mut chain_nodes: Vec<roxmltree::Node> = node.ancestors().collect();
+        chain_nodes.reverse();
+        for n in chain_nodes {
+            if let Some(tr) = n.attribute("transform").and_then(|v| v.parse::<Transform>().ok()) {
+                t = multiply(&t, &tr);
+            }
+        }
+        let mut current: Vec<DVec2> = Vec::new();
+        let mut last = DVec2::ZERO;
+        for segment in SimplifyingPathParser::from(data.as_str()) {
+            let segment = segment.map_err(|e| format!("{}: {}", path, e))?;
+            match segment {
+                SimplePathSegment::MoveTo { x, y } => {
+                    if current.len() > 1 {
+                        runs.push(current);
+                    }
+                    last = dvec2(x, y);
+                    current = vec![apply(&t, last)];
+                }
+                SimplePathSegment::LineTo { x, y } => {
+                    last = dvec2(x, y);
+                    current.push(apply(&t, last));
+                }
+                SimplePathSegment::Quadratic { x1, y1, x, y } => {
+                    let c = dvec2(x1, y1);
+                    let end = dvec2(x, y);
+                    let c1 = last + 2.0 / 3.0 * (c - last);
+                    let c2 = end + 2.0 / 3.0 * (c - end);
+                    let p0 = *current.last().unwrap();
+                    cubic(p0, apply(&t, c1), apply(&t, c2), apply(&t, end), &mut current);
+                    last = end;
+                }
+                SimplePathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                    let p0 = *current.last().unwrap();
+                    last = dvec2(x, y);
+                    cubic(
+                        p0,
+                        apply(&t, dvec2(x1, y1)),
+                        apply(&t, dvec2(x2, y2)),
+                        apply(&t, last),
+                        &mut current,
+                    );
+                }
+                SimplePathSegment::ClosePath => {
+                    if current.len() > 2 {
+                        closed.push(current);
+                    }
+                    current = Vec::new();
+                }
+            }
+        }
+        if current.len() > 1 {
+            runs.push(current);
+        }
+    }
+    chain(runs, &mut closed);
+    Ok(outlines(closed))
+}
+
+// dxf
+
+pub fn load_dxf(path: &str) -> Result<Vec<Outline>, String> {
+    use dxf::entities::EntityType;
+
+    let drawing = dxf::Drawing::load_file(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut closed = Vec::new();
+    let mut runs = Vec::new();
+    for entity in drawing.entities() {
+        match &entity.specific {
+            EntityType::Line(line) => {
+                runs.push(vec![
+                    dvec2(line.p1.x, line.p1.y),
+                    dvec2(line.p2.x, line.p2.y),
+                ]);
+            }
+            EntityType::Arc(a) => {
+                let center = dvec2(a.center.x, a.center.y);
+                let start = a.start_angle.to_radians();
+                let mut run = vec![center + a.radius * dvec2(start.cos(), start.sin())];
+                arc(center, a.radius, start, a.end_angle.to_radians(), &mut run);
+                runs.push(run);
+            }
+            EntityType::Circle(c) => {
+                let center = dvec2(c.center.x, c.center.y);
+                let mut run = vec![center + dvec2(c.radius, 0.0)];
+                arc(center, c.radius, 0.0, std::f64::consts::TAU, &mut run);
+                closed.push(run);
+            }
+            EntityType::LwPolyline(poly) => {
+                let v = &poly.vertices;
+                if v.len() < 2 {
+                    continue;
+                }
+                let mut run = vec![dvec2(v[0].x, v[0].y)];
+                let count = if poly.is_closed() { v.len() } else { v.len() - 1 };
+                for i in 0..count {
+                    let a = dvec2(v[i].x, v[i].y);
+                    let b = dvec2(v[(i + 1) % v.len()].x, v[(i + 1) % v.len()].y);
+                    bulge(a, b, v[i].bulge, &mut run);
+                }
+                if poly.is_closed() {
+                    closed.push(run);
+                } else {
+                    runs.push(run);
+                }
+            }
+            EntityType::Spline(s) => {
+                let points: Vec<DVec2> = s.control_points.iter().map(|p| dvec2(p.x, p.y)).collect();
+                let degree = s.degree_of_curve.max(0) as usize;
+                // only the one weight is kept , so rational curves
+                // can't be followed
+                let usable = degree >= 1
+                    && !s.is_rational()
+                    && points.len() > degree
+                    && s.knot_values.len() == points.len() + degree + 1;
+                if !usable {
+                    eprintln!(
+                        "{}: skipped a spline that is rational or has no control points",
+                        path
+                    );
+                    continue;
+                }
+                let mut run = Vec::new();
+                spline(degree, &s.knot_values, &points, &mut run);
+                if s.is_closed() || s.is_periodic() {
+                    closed.push(run);
+                } else {
+                    runs.push(run);
+                }
+            }
+            _ => {}
+        }
+    }
+    chain(runs, &mut closed);
+    Ok(outlines(closed))
+}
+
+// a polyline segment with a bulge is an arc ,
+// the bulge is the tan of a quarter of the included angle
+fn bulge(a: DVec2, b: DVec2, bulge: f64, out: &mut Vec<DVec2>) {
+    if bulge.abs() < 1e-9 {
+        out.push(b);
+        return;
+    }
+    let angle = 4.0 * bulge.atan();
+    let chord = b - a;
+    let radius = chord.length() / (2.0 * (angle / 2.0).sin()).abs();
+    // center is off the chord midpoint
+    let h = radius * (angle / 2.0).cos() * angle.signum();
+    let center = (a + b) / 2.0 + h * chord.normalize().perp();
+    let start = (a - center).y.atan2((a - center).x);
+    let n = steps(radius * angle.abs(), radius);
+    for i in 1..=n {
+        let t = start + angle * i as f64 / n as f64;
+        out.push(center + radius * dvec2(t.cos(), t.sin()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the furthest any of `curve` is from the polyline
+    fn worst(curve: &[DVec2], line: &[DVec2]) -> f64 {
+        curve
+            .iter()
+            .map(|p| {
+                line.windows(2)
+                    .map(|w| gap(*p, w[0], w[1]))
+                    .fold(f64::MAX, f64::min)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    fn bezier(p: [DVec2; 4], t: f64) -> DVec2 {
+        let s = 1.0 - t;
+        s * s * s * p[0] + 3.0 * s * s * t * p[1] + 3.0 * s * t * t * p[2] + t * t * t * p[3]
+    }
+
+    #[test]
+    fn gap_to_a_segment() {
+        let (a, b) = (dvec2(0.0, 0.0), dvec2(10.0, 0.0));
+        assert_eq!(gap(dvec2(5.0, 3.0), a, b), 3.0);
+        assert_eq!(gap(dvec2(13.0, 4.0), a, b), 5.0);
+        assert_eq!(gap(dvec2(3.0, 4.0), a, a), 5.0);
+    }
+
+    #[test]
+    fn cubics_stay_within_the_tolerance() {
+        let p = [
+            dvec2(0.0, 0.0),
+            dvec2(0.0, 40.0),
+            dvec2(60.0, -40.0),
+            dvec2(60.0, 0.0),
+        ];
+        let mut line = vec![p[0]];
+        cubic(p[0], p[1], p[2], p[3], &mut line);
+        assert_eq!(*line.last().unwrap(), p[3]);
+        let curve: Vec<DVec2> = (0..=1000).map(|i| bezier(p, i as f64 / 1000.0)).collect();
+        assert!(worst(&curve, &line) <= TOLERANCE);
+        // a straight cubic is one piece
+        let mut flat = Vec::new();
+        let (a, b) = (dvec2(1.0, 0.0), dvec2(2.0, 0.0));
+        cubic(p[0], a, b, dvec2(3.0, 0.0), &mut flat);
+        assert_eq!(flat, [dvec2(3.0, 0.0)]);
+    }
+
+    #[test]
+    fn linear_splines_are_their_control_polygon() {
+        let points = [dvec2(0.0, 0.0), dvec2(10.0, 0.0), dvec2(10.0, 10.0)];
+        let knots = [0.0, 0.0, 1.0, 2.0, 2.0];
+        let mut line = Vec::new();
+        spline(1, &knots, &points, &mut line);
+        assert_eq!(line[0], points[0]);
+        assert_eq!(*line.last().unwrap(), points[2]);
+        assert!(line.iter().any(|p| p.distance(points[1]) < 1e-9));
+        assert!(worst(&line, &points) < 1e-9);
+    }
+
+    #[test]
+    fn clamped_cubic_splines_are_beziers() {
+        let p = [
+            dvec2(0.0, 0.0),
+            dvec2(10.0, 30.0),
+            dvec2(40.0, 30.0),
+            dvec2(50.0, 0.0),
+        ];
+        let knots = [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+        for t in [0.0, 0.3, 0.5, 0.9] {
+            assert!(de_boor(3, &knots, &p, 3, t).distance(bezier(p, t)) < 1e-9);
+        }
+        let mut line = Vec::new();
+        spline(3, &knots, &p, &mut line);
+        assert!(line[0].distance(p[0]) < 1e-9);
+        assert!(line.last().unwrap().distance(p[3]) < 1e-9);
+        let curve: Vec<DVec2> = (0..=1000).map(|i| bezier(p, i as f64 / 1000.0)).collect();
+        assert!(worst(&curve, &line) <= TOLERANCE);
+    }
+
+    #[test]
+    fn bulges_are_arcs() {
+        // a half circle , counter clockwise so it dips below the chord
+        let (a, b) = (dvec2(0.0, 0.0), dvec2(2.0, 0.0));
+        let mut line = vec![a];
+        bulge(a, b, 1.0, &mut line);
+        assert!(line.last().unwrap().distance(b) < 1e-9);
+        for p in &line {
+            assert!((p.distance(dvec2(1.0, 0.0)) - 1.0).abs() < 1e-9);
+        }
+        assert!(line.iter().all(|p| p.y <= 1e-9));
+        assert!(line.iter().any(|p| p.y < -0.9));
+        let mut straight = Vec::new();
+        bulge(a, b, 0.0, &mut straight);
+        assert_eq!(straight, [b]);
+    }
+
+    #[test]
+    fn arcs_stay_within_the_tolerance() {
+        let mut line = vec![dvec2(20.0, 0.0)];
+        arc(DVec2::ZERO, 20.0, 0.0, std::f64::consts::PI, &mut line);
+        assert!(line.last().unwrap().distance(dvec2(-20.0, 0.0)) < 1e-9);
+        for w in line.windows(2) {
+            let middle = (w[0] + w[1]) / 2.0;
+            assert!(20.0 - middle.length() <= TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn runs_chain_into_loops() {
+        let runs = vec![
+            vec![dvec2(0.0, 0.0), dvec2(10.0, 0.0)],
+            // drawn the other way round
+            vec![dvec2(10.0, 10.0), dvec2(10.0, 0.0)],
+            vec![dvec2(10.0, 10.0), dvec2(0.0, 0.0)],
+            // goes nowhere
+            vec![dvec2(50.0, 50.0), dvec2(60.0, 50.0)],
+        ];
+        let mut closed = Vec::new();
+        chain(runs, &mut closed);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].len(), 4);
+    }
+
+    #[test]
+    fn svg_lengths() {
+        assert_eq!(length_mm(Some("10mm")), Some(10.0));
+        assert_eq!(length_mm(Some("2cm")), Some(20.0));
+        assert_eq!(length_mm(Some("1in")), Some(25.4));
+        assert_eq!(length_mm(Some("96")), Some(25.4));
+        assert_eq!(length_mm(Some("3em")), None);
+        assert_eq!(length_mm(None), None);
+    }
+
+    fn page_of(attributes: &str) -> Transform {
+        let text = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" {}/>", attributes);
+        let doc = roxmltree::Document::parse(&text).unwrap();
+        page(&doc.root_element())
+    }
+
+    #[test]
+    fn pages_fit_the_view_box() {
+        let t = page_of(r#"width="100mm" height="50mm" viewBox="10 0 200 100""#);
+        assert_eq!(apply(&t, dvec2(10.0, 0.0)), dvec2(0.0, 0.0));
+        assert_eq!(apply(&t, dvec2(210.0, 100.0)), dvec2(100.0, -50.0));
+        // a wide view box in a square page sits in the middle
+        let square = r#"width="100mm" height="100mm" viewBox="0 0 200 100""#;
+        let t = page_of(square);
+        assert_eq!(apply(&t, dvec2(0.0, 0.0)), dvec2(0.0, -25.0));
+        let t = page_of(&format!("{} preserveAspectRatio=\"xMinYMin\"", square));
+        assert_eq!(apply(&t, dvec2(0.0, 0.0)), dvec2(0.0, 0.0));
+        let t = page_of(&format!("{} preserveAspectRatio=\"none\"", square));
+        assert_eq!(apply(&t, dvec2(200.0, 100.0)), dvec2(100.0, -100.0));
+        // no view box is css pixels
+        let t = page_of("");
+        assert_eq!(apply(&t, dvec2(96.0, 0.0)), dvec2(25.4, 0.0));
+    }
+
+    #[test]
+    fn svg_shapes_become_outlines() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100">
+            <rect x="10" y="10" width="20" height="30"/>
+            <g transform="translate(50 0)"><circle cx="20" cy="50" r="10"/></g>
+            <path d="M 0 0 L 5 5"/>
+        </svg>"#;
+        let path = std::env::temp_dir().join(format!("gridfin-profile-{}.svg", std::process::id()));
+        fs::write(&path, text).unwrap();
+        let outlines = load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        let outlines = outlines.unwrap();
+        assert_eq!(outlines.len(), 2);
+        let circle = outlines
+            .iter()
+            .find(|o| o.points()[0].x > 50.0)
+            .expect("the translated circle");
+        // svg is y down , so the middle of the page is at y = -50 .
+        // the arcs come as cubics , which are a hair off round
+        for p in circle.points() {
+            assert!((p.distance(dvec2(70.0, -50.0)) - 10.0).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+// Print estimates
+// volume and surface area from the mesh of the part , then the
+// filament it takes and a rough idea of how long it prints
+
+use glam::DVec3;
+use serde::Serialize;
+
+use crate::export::Body;
+
+// seconds lost on each layer change , travel and retraction
+const LAYER_CHANGE: f64 = 2.0;
+
+#[derive(Debug, Clone)]
+pub struct Material {
+    // g/cm3
+    pub density: f64,
+    // mm
+    pub layer_height: f64,
+    // volumetric flow in mm3/s
+    pub flow: f64,
+}
+
+impl Material {
+    pub fn new(density: f64, layer_height: f64, flow: f64) -> Self {
+        Self {
+            density,
+            layer_height,
+            flow,
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.density <= 0.0 || self.layer_height <= 0.0 || self.flow <= 0.0 {
+            return Err("density , layer height and flow must be more than zero".to_owned());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub name: String,
+    // mm3
+    pub volume: f64,
+    // mm2
+    pub area: f64,
+    // mm
+    pub height: f64,
+    // g
+    pub mass: f64,
+    // minutes
+    pub time: f64,
+}
+
+impl Report {
+    pub fn new(name: &str, bodies: &[Body], material: &Material) -> Self {
+        let mut volume = 0.0;
+        let mut area = 0.0;
+        let mut low = f64::MAX;
+        let mut high = f64::MIN;
+        for body in bodies {
+            let mesh = body.shape.mesh();
+            let (v, a) = measure(&mesh.vertices, &mesh.indices);
+            volume += v;
+            area += a;
+            for v in &mesh.vertices {
+                low = low.min(v.z);
+                high = high.max(v.z);
+            }
+        }
+        let height = if high > low { high - low } else { 0.0 };
+        Report::estimate(name, volume, area, height, material)
+    }
+
+    // the filament and time for a part of the given size
+    fn estimate(name: &str, volume: f64, area: f64, height: f64, material: &Material) -> Self {
+        let layers = (height / material.layer_height).ceil();
+        Self {
+            name: name.to_owned(),
+            volume,
+            area,
+            height,
+            // mm3 to cm3
+            mass: volume / 1000.0 * material.density,
+            time: (volume / material.flow + layers * LAYER_CHANGE) / 60.0,
+        }
+    }
+
+    // the sum of a batch , the height is the tallest part
+    pub fn total(name: &str, reports: &[Report]) -> Self {
+        let mut total = Self {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        for r in reports {
+            total.volume += r.volume;
+            total.area += r.area;
+            total.height = total.height.max(r.height);
+            total.mass += r.mass;
+            total.time += r.time;
+        }
+        total
+    }
+
+    pub fn text(&self) -> String {
+        format!(
+            "{} : volume {:.1} cm3 , area {:.1} cm2 , {:.1} g , about {}",
+            self.name,
+            self.volume / 1000.0,
+            self.area / 100.0,
+            self.mass,
+            duration(self.time)
+        )
+    }
+
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+// the volume and surface area of a closed triangle mesh
+fn measure(vertices: &[DVec3], indices: &[usize]) -> (f64, f64) {
+    let mut signed: f64 = 0.0;
+    let mut area = 0.0;
+    for t in indices.chunks(3) {
+        let [a, b, c] = [vertices[t[0]], vertices[t[1]], vertices[t[2]]];
+        // signed tetrahedra to the origin add up to the volume
+        signed += a.dot(b.cross(c)) / 6.0;
+        area += (b - a).cross(c - a).length() / 2.0;
+    }
+    // the winding sets the sign , and can differ between bodies
+    (signed.abs(), area)
+}
+
+// "1h 25m" from minutes
+fn duration(minutes: f64) -> String {
+    let minutes = minutes.round() as usize;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::dvec3;
+
+    // a box from the origin to `size` , wound outwards
+    fn cuboid(size: DVec3) -> (Vec<DVec3>, Vec<usize>) {
+        let vertices = (0..8)
+            .map(|i| {
+                let bit = |n: usize| ((i >> n) & 1) as f64;
+                dvec3(bit(0), bit(1), bit(2)) * size
+            })
+            .collect();
+        let quads = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let indices = quads
+            .iter()
+            .flat_map(|[a, b, c, d]| [*a, *b, *c, *a, *c, *d])
+            .collect();
+        (vertices, indices)
+    }
+
+    #[test]
+    fn boxes_measure_up() {
+        let (vertices, indices) = cuboid(dvec3(2.0, 3.0, 4.0));
+        let (volume, area) = measure(&vertices, &indices);
+        assert!((volume - 24.0).abs() < 1e-9);
+        assert!((area - 52.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn volume_ignores_winding_and_origin() {
+        let (vertices, mut indices) = cuboid(DVec3::ONE);
+        indices.reverse();
+        let moved: Vec<DVec3> = vertices
+            .iter()
+            .map(|v| *v + dvec3(50.0, -20.0, 7.0))
+            .collect();
+        assert!((measure(&moved, &indices).0 - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn filament_and_time() {
+        let pla = Material::new(1.25, 0.2, 10.0);
+        let report = Report::estimate("bin", 6000.0, 0.0, 21.0, &pla);
+        assert!((report.mass - 7.5).abs() < 1e-9);
+        // 600s of flow and 105 layer changes
+        assert!((report.time - (600.0 + 105.0 * LAYER_CHANGE) / 60.0).abs() < 1e-9);
+        assert!(Material::new(1.25, 0.0, 10.0).check().is_err());
+    }
+
+    #[test]
+    fn totals_add_up() {
+        let pla = Material::new(1.0, 0.2, 10.0);
+        let a = Report::estimate("a", 1000.0, 10.0, 20.0, &pla);
+        let b = Report::estimate("b", 3000.0, 5.0, 40.0, &pla);
+        let total = Report::total("all", &[a, b]);
+        assert_eq!(
+            (total.volume, total.area, total.height),
+            (4000.0, 15.0, 40.0)
+        );
+        assert_eq!(total.mass, 4.0);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(duration(42.4), "42m");
+        assert_eq!(duration(59.6), "1h 00m");
+        assert_eq!(duration(125.0), "2h 05m");
+    }
+}
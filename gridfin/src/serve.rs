@@ -0,0 +1,90 @@
+// Local generation service
+// a small http server that takes bin , base plate or lid settings
+// as json and sends back the model , finished parts are kept on disk
+// by a hash of the settings so asking twice is free
+
+use std::fs;
+use std::path::Path;
+
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::export::{write, Format};
+use crate::params::Params;
+
+const USAGE: &str = "gridfin\n\nPOST /generate with a json body , for example\n\n  {\"kind\": \"bin\", \"width\": 2, \"length\": 1, \"height\": 3, \"format\": \"stl\"}\n\nkind is bin , baseplate or lid , format is stl , 3mf or step\n";
+
+fn mime(format: Format) -> &'static str {
+    match format {
+        Format::Stl => "model/stl",
+        Format::Step => "model/step",
+        Format::ThreeMf => "model/3mf",
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+// the model for the settings , from the cache if it is there
+fn generate(cache: &Path, params: &Params) -> Result<(String, Vec<u8>), String> {
+    let name = format!("{}.{}", params.key(), params.format.ext());
+    let path = cache.join(&name);
+    if path.exists() {
+        println!("cached : {:?}", name);
+    } else {
+        println!("generate : {:?}", name);
+        // write beside the cache entry and move it in when done , so a
+        // failed run never leaves half a file behind
+        let temp = cache.join(format!("{}.part", name));
+        let temp_name = temp.to_string_lossy().to_string();
+        write(&temp_name, params.format, params.bodies()?)?;
+        fs::rename(&temp, &path).map_err(|e| format!("{:?}: {}", path, e))?;
+    }
+    let data = fs::read(&path).map_err(|e| format!("{:?}: {}", path, e))?;
+    Ok((name, data))
+}
+
+fn handle(cache: &Path, mut request: Request) {
+    let method = request.method().clone();
+    let url = request.url().to_owned();
+    let response = match (&method, url.as_str()) {
+        (Method::Get, "/") => Response::from_string(USAGE).with_status_code(200),
+        (Method::Post, "/generate") => {
+            let mut body = String::new();
+            let result = request
+                .as_reader()
+                .read_to_string(&mut body)
+                .map_err(|e| e.to_string())
+                .and_then(|_| serde_json::from_str::<Params>(&body).map_err(|e| e.to_string()))
+                .and_then(|params| generate(cache, &params).map(|r| (params.format, r)));
+            match result {
+                Ok((format, (name, data))) => Response::from_data(data)
+                    .with_header(header("Content-Type", mime(format)))
+                    .with_header(header(
+                        "Content-Disposition",
+                        &format!("attachment; filename=\"{}\"", name),
+                    )),
+                Err(e) => {
+                    eprintln!("bad request : {}", e);
+                    Response::from_string(format!("{}\n", e)).with_status_code(400)
+                }
+            }
+        }
+        _ => Response::from_string("not found\n").with_status_code(404),
+    };
+    if let Err(e) = request.respond(response) {
+        eprintln!("{}", e);
+    }
+}
+
+// requests are made one at a time , the kernel is not thread safe
+pub fn run(address: &str, cache: &str) -> Result<(), String> {
+    let cache = Path::new(cache);
+    fs::create_dir_all(cache).map_err(|e| format!("{:?}: {}", cache, e))?;
+    let server = Server::http(address).map_err(|e| format!("{}: {}", address, e))?;
+    println!("serving on http://{}", address);
+    for request in server.incoming_requests() {
+        handle(cache, request);
+    }
+    Ok(())
+}
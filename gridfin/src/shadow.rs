@@ -0,0 +1,354 @@
+// Tool shadow interiors
+// a model or an outline is sunk into a filled bin to make a
+// fitted pocket for a tool , with an optional finger hole beside it
+
+use std::path::Path;
+
+use glam::{dvec2, dvec3, DVec2, DVec3};
+use opencascade::{
+    primitives::{Direction, Shape},
+    workplane::Workplane,
+};
+
+use crate::gridfin::{V_UNIT, WALL_THICKNESS};
+use crate::mask::Mask;
+use crate::outline::Outline;
+use crate::profile;
+
+#[derive(Debug, Clone)]
+pub enum ShadowSource {
+    // a solid model , step , brep or stl , and the points of its mesh
+    // for the fit checks . its z = 0 is the top face and it hangs
+    // down from there , the top goes level with the top of the wall
+    Model { path: String, points: Vec<DVec3> },
+    // flat outlines extruded down to the depth
+    Outline(Vec<Outline>),
+}
+
+impl ShadowSource {
+    // pick the source from the file extension
+    pub fn load(path: &str) -> Result<Self, String> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match ext.as_str() {
+            "step" | "stp" | "brep" | "brp" | "stl" => {
+                let points = Shadow::read(path)?.mesh().vertices;
+                if points.is_empty() {
+                    return Err(format!("{}: no faces in the model", path));
+                }
+                Ok(ShadowSource::Model {
+                    path: path.to_owned(),
+                    points,
+                })
+            }
+            // svg , dxf or a plain point list
+            _ => Ok(ShadowSource::Outline(profile::load(path)?)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Shadow {
+    source: ShadowSource,
+    // position of the part origin from the bin center
+    pub pos: DVec2,
+    // rotation about z in degrees
+    pub rotation: f64,
+    // how far the part and the finger hole sink below the top of the
+    // wall , models are cut off below this . none sinks an outline
+    // 10mm and a model its own height
+    pub depth: Option<f64>,
+    // extra gap all the way around the part , none by default for
+    // models as the copies are slow
+    pub clearance: f64,
+    // finger hole (diameter , offset from the part origin)
+    pub finger: Option<(f64, DVec2)>,
+}
+
+impl Shadow {
+    // how many directions the clearance copies are spread in
+    const CLEARANCE_STEPS: usize = 8;
+    // models stand this far proud of the wall
+    const LIFT: f64 = 0.1;
+    // outlines sink this far unless told otherwise
+    const DEPTH: f64 = 10.0;
+
+    pub fn new(source: ShadowSource) -> Self {
+        let clearance = match source {
+            ShadowSource::Outline(_) => 0.5,
+            ShadowSource::Model { .. } => 0.0,
+        };
+        Self {
+            source,
+            pos: dvec2(0.0, 0.0),
+            rotation: 0.0,
+            depth: None,
+            clearance,
+            finger: None,
+        }
+    }
+
+    fn read(path: &str) -> Result<Shape, String> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let shape = match ext.as_str() {
+            "brep" | "brp" => Shape::read_brep(path),
+            "stl" => Shape::read_stl(path),
+            _ => Shape::read_step(path),
+        };
+        shape.map_err(|e| format!("{}: {:?}", path, e))
+    }
+
+    // how far below the top of the wall the pocket goes
+    pub fn sink(&self) -> f64 {
+        match (&self.source, self.depth) {
+            (_, Some(depth)) => depth,
+            (ShadowSource::Model { points, .. }, None) => {
+                -points.iter().map(|p| p.z).fold(0.0, f64::min)
+            }
+            (ShadowSource::Outline(_), None) => Shadow::DEPTH,
+        }
+    }
+
+    // finger hole diameter and center on the bin
+    pub fn finger_hole(&self) -> Option<(f64, DVec2)> {
+        let (diameter, offset) = self.finger?;
+        let pos = self.pos + DVec2::from_angle(self.rotation.to_radians()).rotate(offset);
+        Some((diameter, pos))
+    }
+
+    // the points of the pocket edge on the bin , clearance included
+    fn footprint(&self) -> Vec<DVec2> {
+        let mut points: Vec<DVec2> = match &self.source {
+            ShadowSource::Model { points, .. } => {
+                let turn = DVec2::from_angle(self.rotation.to_radians());
+                // the copies are smeared round by the clearance
+                points
+                    .iter()
+                    .flat_map(|p| around(self.pos + turn.rotate(p.truncate()), self.clearance))
+                    .collect()
+            }
+            ShadowSource::Outline(outlines) => outlines
+                .iter()
+                .flat_map(|o| {
+                    o.offset(self.clearance)
+                        .rotate(self.rotation)
+                        .translate(self.pos)
+                        .points()
+                        .to_vec()
+                })
+                .collect(),
+        };
+        if let Some((diameter, center)) = self.finger_hole() {
+            points.extend(around(center, diameter / 2.0));
+        }
+        points
+    }
+
+    pub fn check(&self, mask: &Mask, height: usize) -> Result<(), String> {
+        let depth = self.sink();
+        if depth <= 0.0 || depth > V_UNIT * height as f64 {
+            return Err(format!(
+                "shadow depth {:.2} does not fit in a bin {} units high",
+                depth, height
+            ));
+        }
+        // keep an open bin's wall all the way round the pocket
+        for p in self.footprint() {
+            if around(p, WALL_THICKNESS).iter().any(|q| !mask.contains(*q)) {
+                return Err(format!(
+                    "the shadow reaches {:.1},{:.1} , closer than {}mm to the edge of the bin",
+                    p.x, p.y, WALL_THICKNESS
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // the cutter for a bin of `height` units
+    pub fn shape(&mut self, mask: &Mask, height: usize) -> Result<Shape, String> {
+        let top = V_UNIT * (height + 1) as f64;
+        self.check(mask, height)?;
+        let depth = self.sink();
+        let mut cutter: Shape = match &self.source {
+            ShadowSource::Outline(outlines) => {
+                let mut pockets = outlines.iter().map(|outline| -> Shape {
+                    let face = outline
+                        .offset(self.clearance)
+                        .rotate(self.rotation)
+                        .translate(self.pos)
+                        .wire()
+                        .translate(dvec3(0.0, 0.0, top - depth))
+                        .to_face();
+                    // run it out past the top so the faces don't touch
+                    face.extrude(dvec3(0.0, 0.0, depth + 1.0)).into()
+                });
+                let mut cutter = pockets.next().unwrap();
+                for pocket in pockets {
+                    cutter = cutter.union(&pocket).into();
+                }
+                cutter
+            }
+            ShadowSource::Model { path, .. } => {
+                let model = Shadow::read(path)?;
+                // the top of the part a little above the top of the wall ,
+                // so the pocket opens and the faces don't touch
+                let origin = dvec3(self.pos.x, self.pos.y, top + Shadow::LIFT);
+                let mut cutter = model.clone();
+                cutter.set_global_transform(origin, DVec3::Z, self.rotation.to_radians());
+                // no offset for solids , so smear copies of the
+                // part around by the clearance instead . this is slow
+                // and can fail on stl meshes , so it is only done when
+                // asked for
+                if self.clearance > 0.0 {
+                    for i in 0..Shadow::CLEARANCE_STEPS {
+                        let a = std::f64::consts::TAU * i as f64 / Shadow::CLEARANCE_STEPS as f64;
+                        let step = self.clearance * dvec3(a.cos(), a.sin(), 0.0);
+                        let mut copy = model.clone();
+                        copy.set_global_transform(
+                            origin + step,
+                            DVec3::Z,
+                            self.rotation.to_radians(),
+                        );
+                        cutter = cutter.union(&copy).into();
+                    }
+                    let mut copy = model.clone();
+                    copy.set_global_transform(
+                        origin - dvec3(0.0, 0.0, self.clearance),
+                        DVec3::Z,
+                        self.rotation.to_radians(),
+                    );
+                    cutter = cutter.union(&copy).into();
+                }
+                // cut off below the depth , the part stands out of
+                // the pocket
+                if self.depth.is_some() {
+                    let reach = self
+                        .footprint()
+                        .iter()
+                        .map(|p| p.x.abs().max(p.y.abs()))
+                        .fold(0.0, f64::max);
+                    let side = 2.0 * reach + 2.0;
+                    let slab: Shape = Workplane::xy()
+                        .rect(side, side)
+                        .translate(dvec3(0.0, 0.0, top - depth))
+                        .to_face()
+                        .extrude(dvec3(0.0, 0.0, depth + 1.0))
+                        .into();
+                    cutter = cutter.intersect(&slab).into();
+                }
+                cutter
+            }
+        };
+        if let Some((diameter, pos)) = self.finger_hole() {
+            let mut finger = Finger::new(diameter, depth);
+            let mut hole = finger.shape();
+            hole.set_global_translation(dvec3(pos.x, pos.y, top - depth));
+            cutter = cutter.union(&hole).into();
+        }
+        Ok(cutter)
+    }
+}
+
+// the four points `r` either side of `p` along x and y
+fn around(p: DVec2, r: f64) -> [DVec2; 4] {
+    [
+        p + dvec2(r, 0.0),
+        p - dvec2(r, 0.0),
+        p + dvec2(0.0, r),
+        p - dvec2(0.0, r),
+    ]
+}
+
+// a round bottomed hole to get a finger under the part
+#[derive(Debug)]
+pub struct Finger {
+    diameter: f64,
+    depth: f64,
+}
+
+impl Finger {
+    pub fn new(diameter: f64, depth: f64) -> Self {
+        Self { diameter, depth }
+    }
+
+    pub fn shape(&mut self) -> Shape {
+        let rim = Workplane::xy().circle(0.0, 0.0, self.diameter / 2.0);
+        let mut hole: Shape = rim
+            .to_face()
+            .extrude(dvec3(0.0, 0.0, self.depth + 1.0))
+            .into();
+        let bot_edges = hole.faces().farthest(Direction::NegZ).edges();
+        let radius = (self.diameter / 2.0).min(self.depth) * 0.9;
+        hole = hole.fillet_edges(radius, bot_edges);
+        hole
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(half: f64) -> ShadowSource {
+        let points = vec![
+            dvec2(-half, -half),
+            dvec2(half, -half),
+            dvec2(half, half),
+            dvec2(-half, half),
+        ];
+        ShadowSource::Outline(vec![Outline::new(points).unwrap()])
+    }
+
+    fn model(points: Vec<DVec3>) -> ShadowSource {
+        ShadowSource::Model {
+            path: "part.step".to_owned(),
+            points,
+        }
+    }
+
+    #[test]
+    fn models_sink_their_own_height() {
+        let mut shadow = Shadow::new(model(vec![dvec3(0.0, 0.0, 0.0), dvec3(5.0, 5.0, -12.0)]));
+        assert_eq!(shadow.sink(), 12.0);
+        shadow.depth = Some(4.0);
+        assert_eq!(shadow.sink(), 4.0);
+        assert_eq!(Shadow::new(square(5.0)).sink(), Shadow::DEPTH);
+    }
+
+    #[test]
+    fn tall_models_are_rejected() {
+        let shadow = Shadow::new(model(vec![dvec3(0.0, 0.0, 0.0), dvec3(0.0, 0.0, -30.0)]));
+        let mask = Mask::rect(1, 1);
+        assert!(shadow.check(&mask, 3).is_err());
+        assert!(shadow.check(&mask, 5).is_ok());
+    }
+
+    #[test]
+    fn shadows_keep_off_the_walls() {
+        let mask = Mask::rect(1, 1);
+        assert!(Shadow::new(square(10.0)).check(&mask, 3).is_ok());
+        // 19 plus the clearance and the wall is past the 20.75 edge
+        let wide = Shadow::new(square(19.0));
+        assert!(wide.check(&mask, 3).is_err());
+        let mut moved = Shadow::new(square(10.0));
+        moved.pos = dvec2(9.0, 0.0);
+        assert!(moved.check(&mask, 3).is_err());
+        assert!(moved.check(&Mask::rect(2, 1), 3).is_ok());
+    }
+
+    #[test]
+    fn finger_holes_keep_off_the_walls() {
+        let mask = Mask::rect(1, 1);
+        let mut shadow = Shadow::new(square(5.0));
+        shadow.finger = Some((10.0, dvec2(14.0, 0.0)));
+        assert!(shadow.check(&mask, 3).is_err());
+        shadow.finger = Some((10.0, dvec2(10.0, 0.0)));
+        assert!(shadow.check(&mask, 3).is_ok());
+    }
+}
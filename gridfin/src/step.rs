@@ -0,0 +1,464 @@
+// Step assemblies
+// every part is moved to its place with `set_global_translation` and
+// written on its own , then the files are stitched together under one
+// assembly product with each part as a named product , nothing gets fused
+
+use std::fs;
+
+use glam::DVec3;
+use opencascade::primitives::Shape;
+
+pub struct Part {
+    pub name: String,
+    pub shape: Shape,
+    // where the part origin goes in the assembly
+    pub pos: DVec3,
+}
+
+impl Part {
+    pub fn new(name: &str, shape: Shape, pos: DVec3) -> Self {
+        Self {
+            name: name.to_owned(),
+            shape,
+            pos,
+        }
+    }
+}
+
+// one `#id = body` record from the data section
+struct Entity {
+    id: usize,
+    body: String,
+}
+
+// the data section records , split on `;` outside of strings
+fn entities(text: &str) -> Result<Vec<Entity>, String> {
+    let start = text.find("DATA;").ok_or("step file has no data section")? + 5;
+    let end = text[start..].find("ENDSEC;").ok_or("step file has no end")? + start;
+    let mut records = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text[start..end].chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ';' if !quoted => {
+                records.push(std::mem::take(&mut current));
+            }
+            '\n' | '\r' if !quoted => {}
+            _ => current.push(c),
+        }
+    }
+    records
+        .iter()
+        .map(|r| r.trim())
+        .filter(|r| !r.is_empty())
+        .map(|r| {
+            let (id, body) = r.split_once('=').ok_or(format!("bad step record {:?}", r))?;
+            let id = id
+                .trim()
+                .trim_start_matches('#')
+                .parse()
+                .map_err(|_| format!("bad step id {:?}", id))?;
+            Ok(Entity {
+                id,
+                body: body.trim().to_owned(),
+            })
+        })
+        .collect()
+}
+
+// move every `#n` reference outside of strings up by `offset`
+fn renumber(body: &str, offset: usize) -> String {
+    let mut out = String::new();
+    let mut chars = body.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        out.push(c);
+        if c == '\'' {
+            quoted = !quoted;
+        } else if c == '#' && !quoted {
+            let mut digits = String::new();
+            while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(*d);
+                chars.next();
+            }
+            if let Ok(n) = digits.parse::<usize>() {
+                out.push_str(&(n + offset).to_string());
+            }
+        }
+    }
+    out
+}
+
+// the top level arguments of `NAME(a,b,(c,d))`
+fn args(body: &str) -> Vec<String> {
+    let Some(open) = body.find('(') else {
+        return Vec::new();
+    };
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    for c in body[open + 1..].chars() {
+        match c {
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted && depth == 0 => break,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                args.push(std::mem::take(&mut current).trim().to_owned());
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    args.push(current.trim().to_owned());
+    args
+}
+
+fn reference(arg: &str) -> Option<usize> {
+    arg.trim().strip_prefix('#')?.parse().ok()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\'', "''")
+}
+
+// the bits of a part file the assembly has to point at
+struct Links {
+    product_definition: usize,
+    representation: usize,
+    axis: usize,
+    context: usize,
+    product_context: usize,
+    definition_context: usize,
+}
+
+// the one record of a kind , a part file with more than one is not
+// a single product and can't be stitched
+fn find(list: &[Entity], name: &str) -> Result<usize, String> {
+    let prefix = format!("{}(", name);
+    let mut found = list
+        .iter()
+        .filter(|e| e.body.replace(' ', "").starts_with(&prefix));
+    let first = found.next().ok_or(format!("step part has no {}", name))?;
+    if found.next().is_some() {
+        return Err(format!("step part has more than one {}", name));
+    }
+    Ok(first.id)
+}
+
+fn links(list: &[Entity]) -> Result<Links, String> {
+    let body = |id: usize| list.iter().find(|e| e.id == id).map(|e| e.body.as_str());
+    let missing = |what: &str| format!("step part has no {}", what);
+    // SHAPE_DEFINITION_REPRESENTATION(definition , used_representation)
+    let sdr = find(list, "SHAPE_DEFINITION_REPRESENTATION")?;
+    let representation = args(body(sdr).unwrap())
+        .get(1)
+        .and_then(|a| reference(a))
+        .ok_or(missing("representation"))?;
+    // a representation is (name , items , context_of_items)
+    let rep_args = args(body(representation).ok_or(missing("representation"))?);
+    let [_, items, context] = rep_args.as_slice() else {
+        return Err(format!("bad step representation #{}", representation));
+    };
+    let axis = items
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .filter_map(reference)
+        .find(|id| {
+            body(*id)
+                .map(|b| b.replace(' ', "").starts_with("AXIS2_PLACEMENT_3D("))
+                .unwrap_or(false)
+        })
+        .ok_or(missing("placement"))?;
+    Ok(Links {
+        product_definition: find(list, "PRODUCT_DEFINITION")?,
+        representation,
+        axis,
+        context: reference(context).ok_or(missing("context"))?,
+        product_context: find(list, "PRODUCT_CONTEXT")?,
+        definition_context: find(list, "PRODUCT_DEFINITION_CONTEXT")?,
+    })
+}
+
+// the part files , named , under one assembly product called `name`
+// the parts are already in place so every transform is the identity
+fn stitch(name: &str, files: &[(String, String)]) -> Result<String, String> {
+    let mut header = String::new();
+    let mut data: Vec<Entity> = Vec::new();
+    let mut placed: Vec<(Links, &str)> = Vec::new();
+    let mut next = 1;
+    for (part_name, text) in files {
+        if header.is_empty() {
+            header = text[..text.find("DATA;").unwrap_or(0)].to_owned();
+        }
+        let offset = next - 1;
+        let mut list: Vec<Entity> = entities(text)?
+            .into_iter()
+            .map(|e| Entity {
+                id: e.id + offset,
+                body: renumber(&e.body, offset),
+            })
+            .collect();
+        let links = links(&list)?;
+        // name the product after the part
+        let product = find(&list, "PRODUCT")?;
+        for e in list.iter_mut().filter(|e| e.id == product) {
+            let a = args(&e.body);
+            e.body = format!(
+                "PRODUCT('{0}','{0}',{1},{2})",
+                escape(part_name),
+                a[2],
+                a[3]
+            );
+        }
+        next = list.iter().map(|e| e.id).max().unwrap_or(next) + 1;
+        placed.push((links, part_name));
+        data.extend(list);
+    }
+    // the assembly product , sharing the contexts of the first part
+    let first = &placed.first().ok_or("nothing to write")?.0;
+    let mut add = |body: String| {
+        data.push(Entity { id: next, body });
+        next += 1;
+        next - 1
+    };
+    let product = add(format!(
+        "PRODUCT('{0}','{0}','',(#{1}))",
+        escape(name),
+        first.product_context
+    ));
+    let formation = add(format!("PRODUCT_DEFINITION_FORMATION('','',#{})", product));
+    let definition = add(format!(
+        "PRODUCT_DEFINITION('design','',#{},#{})",
+        formation, first.definition_context
+    ));
+    let definition_shape = add(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", definition));
+    let origin = add("CARTESIAN_POINT('',(0.,0.,0.))".to_owned());
+    let z = add("DIRECTION('',(0.,0.,1.))".to_owned());
+    let x = add("DIRECTION('',(1.,0.,0.))".to_owned());
+    let root_axis = add(format!("AXIS2_PLACEMENT_3D('',#{},#{},#{})", origin, z, x));
+    let representation = add(format!(
+        "SHAPE_REPRESENTATION('',(#{}),#{})",
+        root_axis, first.context
+    ));
+    add(format!(
+        "SHAPE_DEFINITION_REPRESENTATION(#{},#{})",
+        definition_shape, representation
+    ));
+    // each part used once
+    for (n, (links, part_name)) in placed.iter().enumerate() {
+        let usage = add(format!(
+            "NEXT_ASSEMBLY_USAGE_OCCURRENCE('{0}','{1}','',#{2},#{3},$)",
+            n + 1,
+            escape(part_name),
+            definition,
+            links.product_definition
+        ));
+        let usage_shape = add(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", usage));
+        let transform = add(format!(
+            "ITEM_DEFINED_TRANSFORMATION('','',#{},#{})",
+            links.axis, root_axis
+        ));
+        let relation = add(format!(
+            "( REPRESENTATION_RELATIONSHIP('','',#{},#{}) REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION(#{}) SHAPE_REPRESENTATION_RELATIONSHIP() )",
+            links.representation, representation, transform
+        ));
+        add(format!(
+            "CONTEXT_DEPENDENT_SHAPE_REPRESENTATION(#{},#{})",
+            relation, usage_shape
+        ));
+    }
+    let mut text = header;
+    text.push_str("DATA;\n");
+    for e in &data {
+        text.push_str(&format!("#{} = {};\n", e.id, e.body));
+    }
+    text.push_str("ENDSEC;\nEND-ISO-10303-21;\n");
+    Ok(text)
+}
+
+pub fn write_assembly(path: &str, name: &str, parts: Vec<Part>) -> Result<(), String> {
+    if parts.is_empty() {
+        return Err("nothing to write".to_owned());
+    }
+    let err = |e: &dyn std::fmt::Display| format!("{}: {}", path, e);
+    let mut files = Vec::new();
+    for (i, part) in parts.into_iter().enumerate() {
+        // write the part in its place on its own and read it back
+        let mut shape = part.shape;
+        shape.set_global_translation(part.pos);
+        let temp = std::env::temp_dir().join(format!("gridfin_{}_{}.step", std::process::id(), i));
+        let temp_name = temp.to_string_lossy().to_string();
+        shape
+            .write_step(&temp_name)
+            .map_err(|e| format!("{}: {:?}", temp_name, e))?;
+        let text = fs::read_to_string(&temp).map_err(|e| err(&e))?;
+        fs::remove_file(&temp).ok();
+        files.push((part.name, text));
+    }
+    fs::write(path, stitch(name, &files)?).map_err(|e| err(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::dvec3;
+    use opencascade::workplane::Workplane;
+
+    // the records a part file from open cascade has , cut down
+    const PART: &str = "ISO-10303-21;
+HEADER;
+FILE_NAME('part','',(''),(''),'','','');
+ENDSEC;
+DATA;
+#1 = PRODUCT_CONTEXT('',#2,'mechanical');
+#2 = APPLICATION_CONTEXT('core data');
+#3 = PRODUCT('Open CASCADE','Open CASCADE','',(#1));
+#4 = PRODUCT_DEFINITION_FORMATION('','',#3);
+#5 = PRODUCT_DEFINITION_CONTEXT('part definition',#2,'design');
+#6 = PRODUCT_DEFINITION('design','',#4,#5);
+#7 = PRODUCT_DEFINITION_SHAPE('','',#6);
+#8 = SHAPE_DEFINITION_REPRESENTATION(#7,#9);
+#9 = ADVANCED_BREP_SHAPE_REPRESENTATION('',(#10,#14),
+  #15);
+#10 = AXIS2_PLACEMENT_3D('',#11,#12,#13);
+#11 = CARTESIAN_POINT('',(0.,0.,0.));
+#12 = DIRECTION('',(0.,0.,1.));
+#13 = DIRECTION('',(1.,0.,0.));
+#14 = MANIFOLD_SOLID_BREP('it''s; #2',#16);
+#15 = ( GEOMETRIC_REPRESENTATION_CONTEXT(3) REPRESENTATION_CONTEXT('Context #1','3D') );
+#16 = CLOSED_SHELL('',());
+ENDSEC;
+END-ISO-10303-21;
+";
+
+    #[test]
+    fn records_split_outside_strings() {
+        let list = entities(PART).unwrap();
+        assert_eq!(list.len(), 16);
+        assert_eq!(list.last().unwrap().id, 16);
+        assert_eq!(
+            list[8].body,
+            "ADVANCED_BREP_SHAPE_REPRESENTATION('',(#10,#14),  #15)"
+        );
+        assert_eq!(list[13].body, "MANIFOLD_SOLID_BREP('it''s; #2',#16)");
+        assert!(entities("ISO-10303-21;").is_err());
+    }
+
+    #[test]
+    fn renumber_skips_strings() {
+        assert_eq!(
+            renumber("FOO('#1 it''s',#2,(#3,#10))", 5),
+            "FOO('#1 it''s',#7,(#8,#15))"
+        );
+        assert_eq!(renumber("FOO(#,'#')", 5), "FOO(#,'#')");
+    }
+
+    #[test]
+    fn top_level_args() {
+        assert_eq!(
+            args("NAME('a,(b',#1,(#2,(#3)))"),
+            ["'a,(b'", "#1", "(#2,(#3))"]
+        );
+        assert!(args("NAME").is_empty());
+        assert_eq!(escape("it's"), "it''s");
+    }
+
+    #[test]
+    fn links_follow_renumbering() {
+        let offset = 100;
+        let list: Vec<Entity> = entities(PART)
+            .unwrap()
+            .into_iter()
+            .map(|e| Entity {
+                id: e.id + offset,
+                body: renumber(&e.body, offset),
+            })
+            .collect();
+        let links = links(&list).unwrap();
+        assert_eq!(links.product_definition, 106);
+        assert_eq!(links.representation, 109);
+        assert_eq!(links.axis, 110);
+        assert_eq!(links.context, 115);
+        assert_eq!(links.product_context, 101);
+        assert_eq!(links.definition_context, 105);
+    }
+
+    #[test]
+    fn links_need_a_shape() {
+        let list = entities(&PART.replace("SHAPE_DEFINITION_REPRESENTATION", "X")).unwrap();
+        assert_eq!(
+            links(&list).err().unwrap(),
+            "step part has no SHAPE_DEFINITION_REPRESENTATION"
+        );
+    }
+
+    #[test]
+    fn parts_must_be_single_products() {
+        let last = "#16 = CLOSED_SHELL('',());";
+        let extra = format!("{}\n#17 = PRODUCT_DEFINITION('design','',#4,#5);", last);
+        let list = entities(&PART.replace(last, &extra)).unwrap();
+        assert_eq!(
+            links(&list).err().unwrap(),
+            "step part has more than one PRODUCT_DEFINITION"
+        );
+    }
+
+    fn count(text: &str, name: &str) -> usize {
+        entities(text)
+            .unwrap()
+            .iter()
+            .filter(|e| e.body.starts_with(name))
+            .count()
+    }
+
+    #[test]
+    fn parts_become_named_products() {
+        let files = [
+            ("baseplate".to_owned(), PART.to_owned()),
+            ("bin_1".to_owned(), PART.to_owned()),
+        ];
+        let text = stitch("drawer", &files).unwrap();
+        // two parts and the assembly
+        assert_eq!(count(&text, "PRODUCT("), 3);
+        assert_eq!(count(&text, "NEXT_ASSEMBLY_USAGE_OCCURRENCE("), 2);
+        assert!(text.contains("PRODUCT('bin_1','bin_1','',(#17))"));
+        assert!(text.contains("PRODUCT('drawer','drawer','',(#1))"));
+        // the second part points at its own records
+        assert!(text.contains("#24 = SHAPE_DEFINITION_REPRESENTATION(#23,#25);"));
+        assert!(stitch("drawer", &[]).is_err());
+    }
+
+    #[test]
+    fn assemblies_read_back_in_place() {
+        let block = |size: f64| -> Shape {
+            Workplane::xy()
+                .rect(size, size)
+                .to_face()
+                .extrude(dvec3(0.0, 0.0, size))
+                .into()
+        };
+        let parts = vec![
+            Part::new("small", block(10.0), DVec3::ZERO),
+            Part::new("moved", block(20.0), dvec3(50.0, 0.0, 5.0)),
+        ];
+        let path = std::env::temp_dir().join(format!("gridfin-step-{}.step", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        write_assembly(&path, "drawer", parts).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let shape = Shape::read_step(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(count(&text, "NEXT_ASSEMBLY_USAGE_OCCURRENCE("), 2);
+        assert!(text.contains("PRODUCT('moved','moved'"));
+        // the moved block ends up where it was put
+        let points = shape.unwrap().mesh().vertices;
+        let top = points.iter().fold(DVec3::splat(f64::MIN), |m, p| m.max(*p));
+        assert!((top - dvec3(60.0, 10.0, 25.0)).length() < 1e-3, "{}", top);
+    }
+}
@@ -0,0 +1,395 @@
+// Text labels
+// glyph outlines from a truetype font become outlines , and
+// are engraved into or embossed onto the bin
+
+use std::fs;
+
+use clap::ValueEnum;
+use glam::{dvec2, dvec3, DVec2, DVec3};
+use opencascade::primitives::Shape;
+use ttf_parser::OutlineBuilder;
+
+use crate::gridfin::{stack_height, Connector, Walls, MAGNET_DIAMETER, MID_LIFT, V_UNIT};
+use crate::mask::{inside, Mask};
+use crate::outline::Outline;
+use crate::profile;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Place {
+    // on top of the floor inside the bin
+    Floor,
+    // on the outside of the front wall
+    Front,
+    // on a ledge along the inside of the back wall , level with the
+    // top of the walls so it reads from above
+    Shelf,
+    // under the bin , mirrored so it reads from below
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// collects the glyph contours , scaled to mm
+struct Contours {
+    scale: f64,
+    offset: DVec2,
+    current: Vec<DVec2>,
+    done: Vec<Vec<DVec2>>,
+}
+
+impl Contours {
+    fn point(&self, x: f32, y: f32) -> DVec2 {
+        self.offset + self.scale * dvec2(x as f64, y as f64)
+    }
+}
+
+impl OutlineBuilder for Contours {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.current = vec![self.point(x, y)];
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.current.push(p);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let p0 = *self.current.last().unwrap();
+        let c = self.point(x1, y1);
+        let end = self.point(x, y);
+        let c1 = p0 + 2.0 / 3.0 * (c - p0);
+        let c2 = end + 2.0 / 3.0 * (c - end);
+        profile::cubic(p0, c1, c2, end, &mut self.current);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let p0 = *self.current.last().unwrap();
+        let c1 = self.point(x1, y1);
+        let c2 = self.point(x2, y2);
+        let end = self.point(x, y);
+        profile::cubic(p0, c1, c2, end, &mut self.current);
+    }
+
+    fn close(&mut self) {
+        let contour = std::mem::take(&mut self.current);
+        if contour.len() > 2 {
+            self.done.push(contour);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Label {
+    pub text: String,
+    pub font: String,
+    // em size in mm
+    pub size: f64,
+    pub depth: f64,
+    pub place: Place,
+    pub align: Align,
+    // raised letters , cut into the bin if false
+    pub emboss: bool,
+}
+
+impl Label {
+    // keep this much floor under an engraving
+    const MIN_FLOOR: f64 = 0.6;
+    // magnet holes are this deep
+    const MAGNET_DEPTH: f64 = 2.0;
+    // how far the label shelf reaches into the bin
+    const SHELF: f64 = 12.0;
+
+    pub fn new(text: &str, font: &str) -> Self {
+        Self {
+            text: text.to_owned(),
+            font: font.to_owned(),
+            size: 6.0,
+            depth: 0.6,
+            place: Place::Floor,
+            align: Align::Center,
+            emboss: false,
+        }
+    }
+
+    // the glyph contours laid out along a line , baseline at y = 0
+    // and the cap height centered on the origin
+    fn contours(&self) -> Result<Vec<Vec<DVec2>>, String> {
+        let data = fs::read(&self.font).map_err(|e| format!("{}: {}", self.font, e))?;
+        let face = ttf_parser::Face::parse(&data, 0).map_err(|e| format!("{}: {}", self.font, e))?;
+        let scale = self.size / face.units_per_em() as f64;
+        let cap = face.capital_height().unwrap_or(face.ascender()) as f64 * scale;
+        let mut contours = Contours {
+            scale,
+            offset: dvec2(0.0, -cap / 2.0),
+            current: Vec::new(),
+            done: Vec::new(),
+        };
+        for c in self.text.chars() {
+            let Some(glyph) = face.glyph_index(c) else {
+                continue;
+            };
+            face.outline_glyph(glyph, &mut contours);
+            let advance = face.glyph_hor_advance(glyph).unwrap_or(0) as f64 * scale;
+            contours.offset.x += advance;
+        }
+        let width = contours.offset.x;
+        let shift = match self.align {
+            Align::Left => 0.0,
+            Align::Center => -width / 2.0,
+            Align::Right => -width,
+        };
+        Ok(contours
+            .done
+            .into_iter()
+            .map(|c| c.into_iter().map(|p| p + dvec2(shift, 0.0)).collect())
+            .collect())
+    }
+
+    // the letters as a solid on a plane , pushed out along `through`
+    // contours inside an odd number of others are the holes in letters
+    fn solid(&self, origin: DVec3, u: DVec3, v: DVec3, through: DVec3) -> Result<Shape, String> {
+        let contours = self.contours()?;
+        let mut solid: Option<Shape> = None;
+        let mut holes: Vec<Shape> = Vec::new();
+        for (i, contour) in contours.iter().enumerate() {
+            let depth = contours
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| *j != i && inside(contour[0], other))
+                .count();
+            let Ok(outline) = Outline::new(contour.clone()) else {
+                continue;
+            };
+            let prism = outline.prism(origin, u, v, through);
+            if depth % 2 == 0 {
+                solid = Some(match solid {
+                    Some(s) => s.union(&prism).into(),
+                    None => prism,
+                });
+            } else {
+                holes.push(prism);
+            }
+        }
+        let mut solid = solid.ok_or(format!("no glyphs for {:?} in {}", self.text, self.font))?;
+        for hole in holes {
+            solid = solid.subtract(&hole).into();
+        }
+        Ok(solid)
+    }
+
+    // the longest edge along the front or the back of the outline ,
+    // from left to right
+    fn edge(mask: &Mask, back: bool) -> (DVec2, DVec2) {
+        let corners = mask.polygon(0.0);
+        let ys = corners.iter().map(|c| c.y);
+        let y = if back {
+            ys.fold(f64::MIN, f64::max)
+        } else {
+            ys.fold(f64::MAX, f64::min)
+        };
+        let n = corners.len();
+        (0..n)
+            .map(|i| (corners[i], corners[(i + 1) % n]))
+            .filter(|(a, b)| (a.y - y).abs() < 1e-6 && (b.y - y).abs() < 1e-6)
+            .max_by(|x, y| x.0.distance(x.1).total_cmp(&y.0.distance(y.1)))
+            .map(|(a, b)| if a.x < b.x { (a, b) } else { (b, a) })
+            .unwrap()
+    }
+
+    // the box around the letters as they are laid out
+    fn bounds(&self) -> Result<(DVec2, DVec2), String> {
+        let contours = self.contours()?;
+        if contours.is_empty() {
+            return Err(format!("no glyphs for {:?} in {}", self.text, self.font));
+        }
+        let points = contours.iter().flatten();
+        let min = points.clone().fold(DVec2::splat(f64::MAX), |m, p| m.min(*p));
+        let max = points.fold(DVec2::splat(f64::MIN), |m, p| m.max(*p));
+        Ok((min, max))
+    }
+
+    pub fn check(&self, mask: &Mask, walls: &Walls, height: usize) -> Result<(), String> {
+        if self.depth <= 0.0 {
+            return Err("label depth must be more than zero".to_owned());
+        }
+        let (min, max) = self.bounds()?;
+        let size = max - min;
+        match self.place {
+            Place::Floor => {
+                // in the middle of the bin , clear of the walls
+                if !mask.holds(walls.thickness, min, max) {
+                    return Err(format!(
+                        "a {:.1} x {:.1}mm label does not fit on the floor",
+                        size.x, size.y
+                    ));
+                }
+                let floor = V_UNIT - MID_LIFT + walls.floor;
+                if !self.emboss && self.depth > floor - Label::MIN_FLOOR {
+                    return Err(format!(
+                        "engraving {}mm deep goes through a {:.2}mm floor",
+                        self.depth, floor
+                    ));
+                }
+            }
+            Place::Front => {
+                // on the flat of the wall , off the rounded corners and
+                // between the feet and the lip
+                let (a, b) = Label::edge(mask, false);
+                let mid = (a.x + b.x) / 2.0;
+                let wall = V_UNIT * height as f64;
+                if mid + min.x < a.x + walls.fillet
+                    || mid + max.x > b.x - walls.fillet
+                    || max.y.max(-min.y) > wall / 2.0
+                {
+                    return Err(format!(
+                        "a {:.1} x {:.1}mm label does not fit on the {:.1} x {:.1}mm front wall",
+                        size.x,
+                        size.y,
+                        b.x - a.x - 2.0 * walls.fillet,
+                        wall
+                    ));
+                }
+                if !self.emboss && self.depth >= walls.thickness {
+                    return Err(format!(
+                        "engraving {}mm deep goes through a {}mm wall",
+                        self.depth, walls.thickness
+                    ));
+                }
+            }
+            Place::Shelf => {
+                if self.emboss {
+                    return Err(
+                        "shelf labels can only be engraved , raised letters catch the bin above"
+                            .to_owned(),
+                    );
+                }
+                if walls.depth(height) < Label::SHELF {
+                    return Err(format!(
+                        "a label shelf needs a bin more than {}mm deep inside",
+                        Label::SHELF
+                    ));
+                }
+                // between the side walls and across the top of the shelf
+                let (a, b) = Label::edge(mask, true);
+                let mid = (a.x + b.x) / 2.0;
+                let side = walls.thickness + walls.inner_fillet();
+                if mid + min.x < a.x + side
+                    || mid + max.x > b.x - side
+                    || max.y.max(-min.y) > Label::SHELF / 2.0
+                {
+                    return Err(format!(
+                        "a {:.1} x {:.1}mm label does not fit on the {:.1} x {}mm shelf",
+                        size.x,
+                        size.y,
+                        b.x - a.x - 2.0 * side,
+                        Label::SHELF
+                    ));
+                }
+                // the underside slopes up to the front edge , so the
+                // shelf is thinnest under the front of the letters
+                let under = Label::SHELF / 2.0 + min.y;
+                if self.depth > under - Label::MIN_FLOOR {
+                    return Err(format!(
+                        "engraving {}mm deep goes through the {:.2}mm shelf under the letters",
+                        self.depth, under
+                    ));
+                }
+            }
+            Place::Bottom => {
+                if self.emboss {
+                    return Err("bottom labels can only be engraved".to_owned());
+                }
+                // inside the magnet and screw holes , which are
+                // inside the foot
+                let clear = Connector::magnets()[0].x - MAGNET_DIAMETER / 2.0;
+                let reach = min.abs().max(max.abs()).max_element();
+                if reach > clear {
+                    return Err(format!(
+                        "the label runs into the magnets under the bin , keep it within {:.1}mm of the middle",
+                        clear
+                    ));
+                }
+                if self.depth >= Label::MAGNET_DEPTH {
+                    return Err(format!(
+                        "engravings under the bin must be less than {}mm deep",
+                        Label::MAGNET_DEPTH
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the ledge for a shelf label , to be unioned onto the bin
+    // before the letters go in , nothing for the other places
+    pub fn shelf(&self, mask: &Mask, walls: &Walls, height: usize) -> Option<Shape> {
+        if self.place != Place::Shelf {
+            return None;
+        }
+        let (a, b) = Label::edge(mask, true);
+        let t = walls.thickness;
+        // a right triangle off the inside of the back wall , the
+        // underside at 45 degrees so it prints without supports
+        let profile = Outline::new(vec![
+            dvec2(0.0, 0.0),
+            dvec2(Label::SHELF, 0.0),
+            dvec2(0.0, -Label::SHELF),
+        ])
+        .ok()?;
+        let origin = dvec3(a.x + t, b.y - t, stack_height(height));
+        let along = (b.x - a.x - 2.0 * t) * DVec3::X;
+        Some(profile.prism(origin, -DVec3::Y, DVec3::Z, along))
+    }
+
+    // the text solid for a bin of `height` units , to be
+    // unioned when embossed or subtracted when engraved
+    pub fn shape(&self, mask: &Mask, walls: &Walls, height: usize) -> Result<Shape, String> {
+        self.check(mask, walls, height)?;
+        // engraved text starts a little proud so the faces don't touch
+        let (lift, depth) = if self.emboss {
+            (0.0, self.depth)
+        } else {
+            (0.1, self.depth + 0.1)
+        };
+        match self.place {
+            Place::Floor => {
+                let origin = dvec3(0.0, 0.0, walls.bottom() + lift);
+                let through = if self.emboss { DVec3::Z } else { -DVec3::Z };
+                self.solid(origin, DVec3::X, DVec3::Y, depth * through)
+            }
+            Place::Front => {
+                let (a, b) = Label::edge(mask, false);
+                let z = V_UNIT + V_UNIT * height as f64 / 2.0;
+                let origin = dvec3((a.x + b.x) / 2.0, a.y - lift, z);
+                let through = if self.emboss { -DVec3::Y } else { DVec3::Y };
+                self.solid(origin, DVec3::X, DVec3::Z, depth * through)
+            }
+            Place::Shelf => {
+                let (a, b) = Label::edge(mask, true);
+                let y = b.y - walls.thickness - Label::SHELF / 2.0;
+                let origin = dvec3((a.x + b.x) / 2.0, y, stack_height(height) + lift);
+                self.solid(origin, DVec3::X, DVec3::Y, -depth * DVec3::Z)
+            }
+            Place::Bottom => {
+                // under the middle cell , clear of the magnets
+                let (x, y) = mask
+                    .cells()
+                    .min_by(|a, b| {
+                        let da = mask.cell_center(a.0, a.1).length();
+                        let db = mask.cell_center(b.0, b.1).length();
+                        da.total_cmp(&db)
+                    })
+                    .unwrap();
+                let center = mask.cell_center(x, y);
+                let origin = dvec3(center.x, center.y, -lift);
+                // mirrored in x so it reads from underneath
+                self.solid(origin, -DVec3::X, DVec3::Y, depth * DVec3::Z)
+            }
+        }
+    }
+}
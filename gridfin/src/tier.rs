@@ -0,0 +1,151 @@
+// Tiered interiors
+// the floor steps up from the front to the back , or ramps up
+// in one slope , so small parts at the back of a drawer show
+
+use clap::ValueEnum;
+use glam::{dvec2, dvec3, DVec3};
+use opencascade::{primitives::Shape, workplane::Workplane};
+use serde::{Deserialize, Serialize};
+
+use crate::gridfin::{Walls, SIZE};
+use crate::mask::Mask;
+use crate::outline::Outline;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Floor {
+    // flat steps
+    Steps,
+    // one ramp from front to back
+    Slope,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tiers {
+    pub floor: Floor,
+    // number of steps , the front one stays on the floor
+    pub count: usize,
+    // height of each step , or the whole ramp for a slope
+    pub rise: f64,
+}
+
+impl Tiers {
+    pub fn new(floor: Floor, count: usize, rise: f64) -> Self {
+        Self { floor, count, rise }
+    }
+
+    // total height the floor comes up at the back
+    fn top(&self) -> f64 {
+        match self.floor {
+            Floor::Steps => self.rise * (self.count.max(1) - 1) as f64,
+            Floor::Slope => self.rise,
+        }
+    }
+
+    // the front edge and height of each raised step , across a bin
+    // `depth` mm from front to back
+    fn steps(&self, depth: f64) -> Vec<(f64, f64)> {
+        let band = depth / self.count as f64;
+        (1..self.count)
+            .map(|k| (band * k as f64 - depth / 2.0, self.rise * k as f64))
+            .collect()
+    }
+
+    pub fn check(&self, walls: &Walls, height: usize) -> Result<(), String> {
+        if self.floor == Floor::Steps && self.count < 2 {
+            return Err("tiers need at least two steps".to_owned());
+        }
+        if self.rise <= 0.0 {
+            return Err("tier rise must be more than zero".to_owned());
+        }
+        if self.top() >= walls.depth(height) {
+            return Err(format!(
+                "tiers rise {}mm in a bin {}mm deep",
+                self.top(),
+                walls.depth(height)
+            ));
+        }
+        Ok(())
+    }
+
+    // the material the steps put back into the cavity , on its floor
+    pub fn shape(&self, mask: &Mask, walls: &Walls) -> Shape {
+        let width = SIZE * mask.width() as f64;
+        let depth = SIZE * mask.height() as f64;
+        let front = -depth / 2.0;
+        match self.floor {
+            Floor::Steps => {
+                // each step runs from its front edge to the back
+                let mut steps: Option<Shape> = None;
+                for (y0, rise) in self.steps(depth) {
+                    let length = depth / 2.0 - y0;
+                    let center = dvec3(0.0, y0 + length / 2.0, walls.bottom());
+                    let rect = Workplane::xy().rect(width, length).translate(center);
+                    let block: Shape = rect.to_face().extrude(dvec3(0.0, 0.0, rise)).into();
+                    steps = Some(match steps {
+                        Some(s) => s.union(&block).into(),
+                        None => block,
+                    });
+                }
+                steps.unwrap()
+            }
+            Floor::Slope => {
+                // a wedge across the whole bin
+                let wedge = Outline::new(vec![
+                    dvec2(front, 0.0),
+                    dvec2(depth / 2.0, 0.0),
+                    dvec2(depth / 2.0, self.rise),
+                ])
+                .unwrap();
+                let origin = dvec3(-width / 2.0, 0.0, walls.bottom());
+                wedge.prism(origin, DVec3::Y, DVec3::Z, dvec3(width, 0.0, 0.0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_split_the_bin_into_bands() {
+        let tiers = Tiers::new(Floor::Steps, 3, 5.0);
+        let depth = 3.0 * SIZE;
+        // the front band stays on the floor
+        let steps = tiers.steps(depth);
+        assert_eq!(steps.len(), 2);
+        assert!((steps[0].0 + SIZE / 2.0).abs() < 1e-9);
+        assert!((steps[1].0 - SIZE / 2.0).abs() < 1e-9);
+        assert_eq!((steps[0].1, steps[1].1), (5.0, 10.0));
+        assert_eq!(tiers.top(), 10.0);
+    }
+
+    #[test]
+    fn slopes_rise_once() {
+        let tiers = Tiers::new(Floor::Slope, 4, 6.0);
+        assert_eq!(tiers.top(), 6.0);
+    }
+
+    fn fits(floor: Floor, count: usize, rise: f64) -> bool {
+        Tiers::new(floor, count, rise)
+            .check(&Walls::default(), 3)
+            .is_ok()
+    }
+
+    #[test]
+    fn tiers_stay_below_the_top() {
+        let depth = Walls::default().depth(3);
+        assert!(fits(Floor::Steps, 2, depth - 0.5));
+        assert!(!fits(Floor::Steps, 2, depth));
+        assert!(!fits(Floor::Steps, 3, depth / 2.0));
+        assert!(!fits(Floor::Slope, 1, depth));
+    }
+
+    #[test]
+    fn bad_tiers() {
+        assert!(!fits(Floor::Steps, 1, 2.0));
+        assert!(fits(Floor::Slope, 1, 2.0));
+        assert!(!fits(Floor::Slope, 1, 0.0));
+    }
+}
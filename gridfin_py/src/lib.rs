@@ -0,0 +1,162 @@
+// Python bindings
+// bins , base plates and lids from keyword arguments named as in the
+// json settings , coming back as parts that write themselves out
+//
+//   import gridfin
+//   gridfin.bin(width=2, length=1, height=3, slot_x=[0]).export("bin.stl")
+
+use std::path::Path;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde_json::{Map, Value};
+
+use gridfin::cache;
+use gridfin::export::{write, Body, Format};
+use gridfin::params::Params;
+
+// the bodies of a finished part , kept apart for 3mf
+#[pyclass(unsendable)]
+struct Part {
+    kind: String,
+    bodies: Vec<Body>,
+}
+
+#[pymethods]
+impl Part {
+    // the format comes from the file extension unless it is given
+    #[pyo3(signature = (path, format = None))]
+    fn export(&self, path: &str, format: Option<&str>) -> PyResult<()> {
+        let name = match format {
+            Some(name) => name,
+            None => Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .ok_or_else(|| PyValueError::new_err(format!("{}: no format", path)))?,
+        };
+        let format = match name.to_lowercase().as_str() {
+            "stl" => Format::Stl,
+            "step" | "stp" => Format::Step,
+            "3mf" => Format::ThreeMf,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "{:?} is not stl , step or 3mf",
+                    name
+                )))
+            }
+        };
+        write(path, format, self.bodies.clone()).map_err(PyValueError::new_err)
+    }
+
+    fn __repr__(&self) -> String {
+        let names: Vec<&str> = self.bodies.iter().map(|b| b.name.as_str()).collect();
+        format!("<gridfin {} {:?}>", self.kind, names)
+    }
+}
+
+// the settings go through json , so the names , defaults and errors
+// are the ones batch manifests and the server have
+fn make(py: Python<'_>, kind: &str, settings: Option<&Bound<'_, PyDict>>) -> PyResult<Part> {
+    let settings = match settings {
+        Some(settings) => settings.clone(),
+        None => PyDict::new(py),
+    };
+    let json: String = py
+        .import("json")?
+        .call_method1("dumps", (settings,))?
+        .extract()?;
+    let params = parse(kind, &json).map_err(PyValueError::new_err)?;
+    let bodies = params.bodies().map_err(PyValueError::new_err)?;
+    Ok(Part {
+        kind: kind.to_owned(),
+        bodies,
+    })
+}
+
+// the keywords as json settings for a `kind` of part , a keyword the
+// kind doesn't take is an error rather than being left out
+fn parse(kind: &str, json: &str) -> Result<Params, String> {
+    let mut settings: Map<String, Value> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    for key in ["kind", "name", "format"] {
+        if settings.contains_key(key) {
+            return Err(format!("{} is not a setting here", key));
+        }
+    }
+    settings.insert("kind".to_owned(), kind.into());
+    let params: Params =
+        serde_json::from_value(Value::Object(settings)).map_err(|e| e.to_string())?;
+    params.unused()?;
+    Ok(params)
+}
+
+#[pyfunction]
+#[pyo3(signature = (**settings))]
+fn bin(py: Python<'_>, settings: Option<&Bound<'_, PyDict>>) -> PyResult<Part> {
+    make(py, "bin", settings)
+}
+
+#[pyfunction]
+#[pyo3(signature = (**settings))]
+fn baseplate(py: Python<'_>, settings: Option<&Bound<'_, PyDict>>) -> PyResult<Part> {
+    make(py, "baseplate", settings)
+}
+
+#[pyfunction]
+#[pyo3(signature = (**settings))]
+fn lid(py: Python<'_>, settings: Option<&Bound<'_, PyDict>>) -> PyResult<Part> {
+    make(py, "lid", settings)
+}
+
+#[pymodule]
+#[pyo3(name = "gridfin")]
+fn gridfin_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    // the same shape cache as the command line
+    cache::set_dir(cache::default_dir());
+    m.add_class::<Part>()?;
+    m.add_function(wrap_pyfunction!(bin, m)?)?;
+    m.add_function(wrap_pyfunction!(baseplate, m)?)?;
+    m.add_function(wrap_pyfunction!(lid, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gridfin::base::Bottom;
+    use gridfin::params::Kind;
+
+    #[test]
+    fn keywords_become_settings() {
+        let params = parse("bin", r#"{"width": 2, "height": 3, "slot_x": [0.0]}"#).unwrap();
+        assert_eq!(params.kind, Kind::Bin);
+        assert_eq!((params.width, params.length, params.height), (2, 1, 3));
+        assert_eq!(params.slot_x, [0.0]);
+        let params = parse("baseplate", r#"{"bottom": "solid"}"#).unwrap();
+        assert_eq!(params.kind, Kind::Baseplate);
+        assert_eq!(params.bottom, Bottom::Solid);
+        assert_eq!(parse("lid", "{}").unwrap().kind, Kind::Lid);
+    }
+
+    #[test]
+    fn keywords_must_suit_the_part() {
+        assert_eq!(
+            parse("lid", r#"{"slot_x": [0.0]}"#).unwrap_err(),
+            "slot_x is not a lid setting"
+        );
+        assert_eq!(
+            parse("baseplate", r#"{"height": 6}"#).unwrap_err(),
+            "height is not a base plate setting"
+        );
+        assert_eq!(
+            parse("bin", r#"{"mount": "cleat"}"#).unwrap_err(),
+            "mount is not a bin setting"
+        );
+        assert!(parse("bin", r#"{"colour": "red"}"#).is_err());
+        assert_eq!(
+            parse("bin", r#"{"kind": "lid"}"#).unwrap_err(),
+            "kind is not a setting here"
+        );
+    }
+}